@@ -1,5 +1,11 @@
-use crate::piece::{starting_pieces, Color, Piece, PieceType, Position};
-use std::ops::{Index, IndexMut};
+use crate::{
+    piece::{starting_pieces, Color, Piece, PieceType, Position},
+    variant::VariantKind,
+};
+use std::{
+    fmt,
+    ops::{Index, IndexMut},
+};
 
 #[derive(PartialEq)]
 enum MoveValidity {
@@ -7,14 +13,245 @@ enum MoveValidity {
     ShortCastle,
     LongCastle,
     EnPassant,
-    Invalid,
+    Invalid(MoveError),
 }
 
+///Why a move couldn't be played.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MoveError {
+    NoPiece,
+    NotYourTurn,
+    SameSquare,
+    OwnPieceOnTarget,
+    ///The piece doesn't move that way.
+    InvalidMovement(PieceType),
+    PathBlocked,
+    LeavesKingInCheck,
+    NoCastlingRights,
+    CastlingOutOfCheck,
+    CastlingThroughCheck,
+    NotInPocket,
+    SquareOccupied,
+    PawnDropOnBackRank,
+    ExplodesOwnKing,
+    InvalidPromotion,
+    CaptureRequired,
+    GivesCheck,
+}
+
+impl fmt::Display for MoveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoPiece => write!(f, "There's no piece on that square"),
+            Self::NotYourTurn => write!(f, "It's not that side's turn"),
+            Self::SameSquare => write!(f, "The piece has to move to another square"),
+            Self::OwnPieceOnTarget => write!(f, "A piece can't capture its own side"),
+            Self::InvalidMovement(piece_type) => {
+                let name = match piece_type {
+                    PieceType::None => "piece",
+                    PieceType::Pawn => "pawn",
+                    PieceType::Knight => "knight",
+                    PieceType::Bishop => "bishop",
+                    PieceType::Rook => "rook",
+                    PieceType::Queen => "queen",
+                    PieceType::King => "king",
+                };
+                write!(f, "A {name} can't move like that")
+            }
+            Self::PathBlocked => write!(f, "Another piece is in the way"),
+            Self::LeavesKingInCheck => write!(f, "That would leave the king in check"),
+            Self::NoCastlingRights => write!(f, "The king or rook has already moved"),
+            Self::CastlingOutOfCheck => write!(f, "The king can't castle out of check"),
+            Self::CastlingThroughCheck => write!(f, "The king can't castle through check"),
+            Self::NotInPocket => write!(f, "There's no such piece in the pocket"),
+            Self::SquareOccupied => write!(f, "Pieces can only be dropped on empty squares"),
+            Self::PawnDropOnBackRank => {
+                write!(f, "Pawns can't be dropped on the first or last rank")
+            }
+            Self::ExplodesOwnKing => write!(f, "That would blow up the king"),
+            Self::InvalidPromotion => write!(f, "A pawn can't promote to that piece"),
+            Self::CaptureRequired => write!(f, "A capture has to be made"),
+            Self::GivesCheck => write!(f, "Giving check isn't allowed"),
+        }
+    }
+}
+
+///How a game has ended.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Outcome {
+    ///The given side won by checkmate.
+    Checkmate(Color),
+    Stalemate,
+    ///The given side won because the opponent ran out of time.
+    Timeout(Color),
+    ///A side ran out of time, but the opponent can't possibly checkmate.
+    TimeoutVsInsufficientMaterial,
+    ///A variant's own ending, with the winner if there is one and how the game was won.
+    Variant(Option<Color>, &'static str),
+    ///The given side won because the opponent resigned.
+    Resignation(Color),
+    DrawAgreed,
+}
+
+impl Outcome {
+    ///Returns the result of the game as written in PGN.
+    pub fn result(self) -> &'static str {
+        match self.winner() {
+            Some(Color::White) => "1-0",
+            Some(Color::Black) => "0-1",
+            None => "1/2-1/2",
+        }
+    }
+    ///Returns the side that won, or `None` for a draw.
+    pub fn winner(self) -> Option<Color> {
+        match self {
+            Self::Checkmate(color) | Self::Timeout(color) | Self::Resignation(color) => Some(color),
+            Self::Variant(winner, _) => winner,
+            Self::Stalemate | Self::TimeoutVsInsufficientMaterial | Self::DrawAgreed => None,
+        }
+    }
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let winner = |color: &Color| {
+            if *color == Color::White {
+                "White"
+            } else {
+                "Black"
+            }
+        };
+        match self {
+            Self::Checkmate(color) => write!(f, "Checkmate, {} Wins", winner(color)),
+            Self::Stalemate => write!(f, "Stalemate, It's a Draw"),
+            Self::Timeout(color) => write!(f, "Time Out, {} Wins", winner(color)),
+            Self::TimeoutVsInsufficientMaterial => {
+                write!(f, "Time Out vs Insufficient Material, It's a Draw")
+            }
+            Self::Variant(Some(color), reason) => write!(f, "{reason}, {} Wins", winner(color)),
+            Self::Variant(None, reason) => write!(f, "{reason}, It's a Draw"),
+            Self::Resignation(color) => {
+                write!(f, "{} Resigned, {} Wins", winner(&!*color), winner(color))
+            }
+            Self::DrawAgreed => write!(f, "Draw Agreed, It's a Draw"),
+        }
+    }
+}
+
+///Why a FEN string couldn't be read.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FenError {
+    WrongFieldCount,
+    InvalidPlacement,
+    InvalidTurn,
+    InvalidCastlingRights,
+    InvalidEnPassant,
+    InvalidMoveCounter,
+    InvalidCheckCount,
+    InvalidPocket,
+    ///The FEN was read, but the position can't be played.
+    IllegalPosition(Vec<PositionProblem>),
+}
+
+impl fmt::Display for FenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::WrongFieldCount => write!(f, "FEN needs between 4 and 6 fields"),
+            Self::InvalidPlacement => write!(f, "The piece placement isn't valid"),
+            Self::InvalidTurn => write!(f, "The side to move must be w or b"),
+            Self::InvalidCastlingRights => write!(f, "The castling rights can't be read"),
+            Self::InvalidEnPassant => write!(f, "The en passant square doesn't match the board"),
+            Self::InvalidMoveCounter => write!(f, "The move counters must be numbers"),
+            Self::InvalidCheckCount => write!(f, "The check counts must be written like +1+0"),
+            Self::InvalidPocket => write!(f, "The pieces in hand must be written like [Qp]"),
+            Self::IllegalPosition(problems) => {
+                let problems: Vec<String> = problems.iter().map(ToString::to_string).collect();
+                write!(f, "{}", problems.join(". "))
+            }
+        }
+    }
+}
+
+///Why a position can't be reached in a game.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PositionProblem {
+    MissingKing(Color),
+    ExtraKings(Color),
+    PawnOnBackRank(Position),
+    ///The side that just moved is in check, so the side to move could capture its king.
+    WaitingSideInCheck,
+    ///The king is attacked by more pieces than a single move can give check with.
+    TooManyCheckers,
+    ///The pawn that can be captured en passant couldn't have just moved two squares.
+    InvalidEnPassant,
+    TooManyPawns(Color),
+    ///The side has more pieces than promotions could have given it.
+    TooManyPieces(Color),
+    ///The side has a castling right without its king and rook on their starting rank.
+    InvalidCastlingRights(Color),
+}
+
+impl fmt::Display for PositionProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = |color: &Color| {
+            if *color == Color::White {
+                "White"
+            } else {
+                "Black"
+            }
+        };
+        match self {
+            Self::MissingKing(color) => write!(f, "{} has no king", name(color)),
+            Self::ExtraKings(color) => write!(f, "{} has more than one king", name(color)),
+            Self::PawnOnBackRank(position) => {
+                write!(f, "The pawn on {position} is on the first or last rank")
+            }
+            Self::WaitingSideInCheck => write!(f, "The side not to move can't be in check"),
+            Self::TooManyCheckers => write!(f, "The king is in check from too many pieces"),
+            Self::InvalidEnPassant => {
+                write!(
+                    f,
+                    "The en passant pawn couldn't have just moved two squares"
+                )
+            }
+            Self::TooManyPawns(color) => write!(f, "{} has more than eight pawns", name(color)),
+            Self::TooManyPieces(color) => write!(f, "{} has too many pieces", name(color)),
+            Self::InvalidCastlingRights(color) => {
+                write!(f, "{} can't castle without its king and rook", name(color))
+            }
+        }
+    }
+}
+
+///The pieces that can be held in a pocket, in the order they are shown and written in FEN.
+pub const POCKET_PIECES: [PieceType; 5] = [
+    PieceType::Queen,
+    PieceType::Rook,
+    PieceType::Bishop,
+    PieceType::Knight,
+    PieceType::Pawn,
+];
+
+#[derive(Clone)]
 pub struct Board {
     pieces: [Piece; 64],
     pub turn: Color,
     last_piece_moved: Piece,
+    last_move: Option<(Position, Position)>,
+    ///The piece the last move promoted a pawn to, if it was a promotion.
+    last_promotion: Option<PieceType>,
     previous_pieces: [Piece; 64],
+    halfmove_clock: u32,
+    pub fullmove_number: u32,
+    ///How many times White and Black have given check, which matters in Three-check.
+    checks: [u8; 2],
+    ///How many of each of the `POCKET_PIECES` White and Black hold to drop, which only fills up
+    ///in Crazyhouse.
+    pockets: [[u8; 5]; 2],
+    ///The square the last move's capture blew up, which only happens in Atomic.
+    explosion: Option<Position>,
+    ///The variant whose rules the game is played by.
+    pub variant: VariantKind,
 }
 
 impl Default for Board {
@@ -23,13 +260,491 @@ impl Default for Board {
             pieces: starting_pieces(),
             turn: Color::White,
             last_piece_moved: Piece::new(PieceType::None, Position::new(0, 0), Color::Black),
+            last_move: None,
+            last_promotion: None,
             previous_pieces: starting_pieces(),
+            halfmove_clock: 0,
+            fullmove_number: 1,
+            checks: [0, 0],
+            pockets: [[0; 5]; 2],
+            explosion: None,
+            variant: VariantKind::Standard,
         }
     }
 }
 impl Board {
-    ///Tries to move a piece given a start and end position.
-    pub fn move_piece(&mut self, old_position: Position, new_position: Position) -> Option<String> {
+    ///Creates the Chess960 starting position with the given number from 0 to 959, where 518 is
+    ///the standard starting position.
+    pub fn chess960(number: u16) -> Self {
+        const KNIGHT_PLACEMENTS: [(usize, usize); 10] = [
+            (0, 1),
+            (0, 2),
+            (0, 3),
+            (0, 4),
+            (1, 2),
+            (1, 3),
+            (1, 4),
+            (2, 3),
+            (2, 4),
+            (3, 4),
+        ];
+        let number = usize::from(number % 960);
+        let mut back_rank: [PieceType; 8] = [PieceType::None; 8];
+        back_rank[(number % 4) * 2 + 1] = PieceType::Bishop;
+        back_rank[(number / 4 % 4) * 2] = PieceType::Bishop;
+        // The queen, knights, rooks and king go on the empty squares left, from the a file.
+        let empty_files = |back_rank: &[PieceType; 8]| -> Vec<usize> {
+            (0..8)
+                .filter(|x| back_rank[*x] == PieceType::None)
+                .collect()
+        };
+        back_rank[empty_files(&back_rank)[number / 16 % 6]] = PieceType::Queen;
+        let (first_knight, second_knight) = KNIGHT_PLACEMENTS[number / 96];
+        let files: Vec<usize> = empty_files(&back_rank);
+        back_rank[files[first_knight]] = PieceType::Knight;
+        back_rank[files[second_knight]] = PieceType::Knight;
+        let files: Vec<usize> = empty_files(&back_rank);
+        back_rank[files[0]] = PieceType::Rook;
+        back_rank[files[1]] = PieceType::King;
+        back_rank[files[2]] = PieceType::Rook;
+
+        let mut board = Self {
+            variant: VariantKind::Chess960,
+            ..Self::default()
+        };
+        for (x, piece_type) in back_rank.into_iter().enumerate() {
+            let x = x as u8;
+            board[Position::new(x, 0)] = Piece::new(piece_type, Position::new(x, 0), Color::Black);
+            board[Position::new(x, 7)] = Piece::new(piece_type, Position::new(x, 7), Color::White);
+        }
+        board.previous_pieces = board.pieces;
+        board
+    }
+    ///Creates a board from a position in Forsyth-Edwards Notation, checking that it can be
+    ///played.
+    pub fn from_fen(fen: &str) -> Result<Self, FenError> {
+        Self::from_variant_fen(fen, VariantKind::Standard)
+    }
+    ///Creates a board for a game of the given variant from a position in Forsyth-Edwards
+    ///Notation, checking that it can be played by the variant's rules.
+    pub fn from_variant_fen(fen: &str, variant: VariantKind) -> Result<Self, FenError> {
+        let mut fields: Vec<&str> = fen.split_whitespace().collect();
+        let mut board = Self {
+            variant,
+            ..Self::default()
+        };
+        // Three-check positions end with the checks each side has given, such as `+1+0`.
+        if fields.len() > 4 && fields.last().is_some_and(|field| field.starts_with('+')) {
+            let counts: Vec<u8> = fields
+                .pop()
+                .unwrap_or_default()
+                .split('+')
+                .skip(1)
+                .map(|count| count.parse().map_err(|_| FenError::InvalidCheckCount))
+                .collect::<Result<_, _>>()?;
+            board.checks = counts.try_into().map_err(|_| FenError::InvalidCheckCount)?;
+        }
+        if !(4..=6).contains(&fields.len()) {
+            return Err(FenError::WrongFieldCount);
+        }
+        let mut placement: &str = fields[0];
+        // Crazyhouse positions end the placement with the pieces in hand, such as `[Qp]`.
+        if let Some((pieces, pocket)) = placement
+            .strip_suffix(']')
+            .and_then(|placement| placement.split_once('['))
+        {
+            placement = pieces;
+            board.variant = VariantKind::Crazyhouse;
+            for letter in pocket.chars() {
+                let piece: Piece = Piece::from_fen_char(letter, Position::default())
+                    .ok_or(FenError::InvalidPocket)?;
+                *board
+                    .pocket_slot(piece.color, piece.piece_type)
+                    .ok_or(FenError::InvalidPocket)? += 1;
+            }
+        }
+        let ranks: Vec<&str> = placement.split('/').collect();
+        if ranks.len() != 8 {
+            return Err(FenError::InvalidPlacement);
+        }
+        for (y, rank) in ranks.iter().enumerate() {
+            let mut x: u8 = 0;
+            for letter in rank.chars() {
+                // A piece that was promoted from a pawn is followed by a tilde in Crazyhouse.
+                if letter == '~' {
+                    let position = Position::new(x.wrapping_sub(1), y as u8);
+                    if x == 0 || board[position].piece_type == PieceType::None {
+                        return Err(FenError::InvalidPlacement);
+                    }
+                    board[position].promoted = true;
+                    continue;
+                }
+                if let Some(empty_squares) = letter.to_digit(10) {
+                    for _ in 0..empty_squares {
+                        if x > 7 {
+                            return Err(FenError::InvalidPlacement);
+                        }
+                        let position = Position::new(x, y as u8);
+                        board[position] = Piece::new(PieceType::None, position, Color::White);
+                        x += 1;
+                    }
+                    continue;
+                }
+                if x > 7 {
+                    return Err(FenError::InvalidPlacement);
+                }
+                let position = Position::new(x, y as u8);
+                let mut piece =
+                    Piece::from_fen_char(letter, position).ok_or(FenError::InvalidPlacement)?;
+                // Kings and rooks get back the castling rights the FEN gives them below.
+                piece.has_moved = matches!(piece.piece_type, PieceType::King | PieceType::Rook);
+                board[position] = piece;
+                x += 1;
+            }
+            if x != 8 {
+                return Err(FenError::InvalidPlacement);
+            }
+        }
+        board.turn = match fields[1] {
+            "w" => Color::White,
+            "b" => Color::Black,
+            _ => return Err(FenError::InvalidTurn),
+        };
+        // Castling rights are KQkq for the outermost rooks, or the rook's file as in X-FEN and
+        // Shredder-FEN. A right without its king and rook is listed with the other problems.
+        let mut problems: Vec<PositionProblem> = Vec::new();
+        if fields[2] != "-" {
+            for letter in fields[2].chars() {
+                let (color, y) = if letter.is_ascii_uppercase() {
+                    (Color::White, 7)
+                } else {
+                    (Color::Black, 0)
+                };
+                if !matches!(letter.to_ascii_uppercase(), 'K' | 'Q' | 'A'..='H') {
+                    return Err(FenError::InvalidCastlingRights);
+                }
+                let king: Option<Piece> = (0..8)
+                    .map(|x| board[Position::new(x, y)])
+                    .find(|piece| piece.piece_type == PieceType::King && piece.color == color);
+                let is_own_rook = |x: &u8| {
+                    let piece: Piece = board[Position::new(*x, y)];
+                    piece.piece_type == PieceType::Rook && piece.color == color
+                };
+                let rook_x: Option<u8> = king.and_then(|king| match letter.to_ascii_uppercase() {
+                    'K' => (king.position.x + 1..8).rev().find(is_own_rook),
+                    'Q' => (0..king.position.x).find(is_own_rook),
+                    file => Some(file as u8 - b'A').filter(is_own_rook),
+                });
+                match (king, rook_x) {
+                    (Some(king), Some(rook_x)) => {
+                        board[king.position].has_moved = false;
+                        board[Position::new(rook_x, y)].has_moved = false;
+                    }
+                    _ if problems.contains(&PositionProblem::InvalidCastlingRights(color)) => (),
+                    _ => problems.push(PositionProblem::InvalidCastlingRights(color)),
+                }
+            }
+        }
+        board.last_piece_moved = Piece::new(PieceType::None, Position::new(0, 0), Color::Black);
+        if fields[3] != "-" {
+            let square = Position::from_algebraic(fields[3]).ok_or(FenError::InvalidEnPassant)?;
+            let (pawn_y, pawn_color) = match (square.y, board.turn) {
+                (2, Color::White) => (3, Color::Black),
+                (5, Color::Black) => (4, Color::White),
+                _ => return Err(FenError::InvalidEnPassant),
+            };
+            let pawn = board[Position::new(square.x, pawn_y)];
+            if pawn.piece_type != PieceType::Pawn || pawn.color != pawn_color {
+                return Err(FenError::InvalidEnPassant);
+            }
+            board.last_piece_moved = pawn;
+        }
+        if let Some(halfmove_clock) = fields.get(4) {
+            board.halfmove_clock = halfmove_clock
+                .parse()
+                .map_err(|_| FenError::InvalidMoveCounter)?;
+        }
+        if let Some(fullmove_number) = fields.get(5) {
+            board.fullmove_number = fullmove_number
+                .parse()
+                .map_err(|_| FenError::InvalidMoveCounter)?;
+        }
+        board.previous_pieces = board.pieces;
+        problems.extend(board.validate());
+        if !problems.is_empty() {
+            return Err(FenError::IllegalPosition(problems));
+        }
+        Ok(board)
+    }
+    ///Returns everything that makes the position impossible to reach in a game, or nothing if it
+    ///can be played.
+    pub fn validate(&self) -> Vec<PositionProblem> {
+        let rules = self.variant.rules();
+        let mut problems: Vec<PositionProblem> = Vec::new();
+        for color in [Color::White, Color::Black] {
+            let pieces: Vec<Piece> = self.get_all_color_pieces(color);
+            let count = |piece_type: PieceType| {
+                pieces
+                    .iter()
+                    .filter(|piece| piece.piece_type == piece_type)
+                    .count()
+            };
+            match count(PieceType::King) {
+                _ if !rules.has_royal_king() => (),
+                0 => problems.push(PositionProblem::MissingKing(color)),
+                1 => (),
+                _ => problems.push(PositionProblem::ExtraKings(color)),
+            }
+            // Captured pieces change sides when there are pockets, so either side can have more
+            // of a piece than it started with.
+            if rules.has_pockets() {
+                continue;
+            }
+            let pawns = count(PieceType::Pawn);
+            // Every piece beyond the starting set must have been a pawn that promoted.
+            let promoted_pieces = count(PieceType::Queen).saturating_sub(1)
+                + count(PieceType::Rook).saturating_sub(2)
+                + count(PieceType::Bishop).saturating_sub(2)
+                + count(PieceType::Knight).saturating_sub(2);
+            if pawns > 8 {
+                problems.push(PositionProblem::TooManyPawns(color));
+            } else if pieces.len() > 16 || promoted_pieces > 8 - pawns {
+                problems.push(PositionProblem::TooManyPieces(color));
+            }
+        }
+        for piece in &self.pieces {
+            if piece.piece_type == PieceType::Pawn
+                && (piece.position.y == 0 || piece.position.y == 7)
+            {
+                problems.push(PositionProblem::PawnOnBackRank(piece.position));
+            }
+        }
+        if let Some(en_passant_pawn) = self.en_passant_pawn() {
+            let direction: i8 = if en_passant_pawn.color == Color::White {
+                1
+            } else {
+                -1
+            };
+            let is_path_clear = [1, 2].iter().all(|distance| {
+                let y = en_passant_pawn.position.y as i8 + direction * distance;
+                self[Position::new(en_passant_pawn.position.x, y as u8)].piece_type
+                    == PieceType::None
+            });
+            if !is_path_clear {
+                problems.push(PositionProblem::InvalidEnPassant);
+            }
+        }
+        problems.retain(|problem| rules.is_problem(self, *problem));
+        // Checks can only be looked at once both kings are on the board, and only matter if the
+        // kings have to be kept out of check.
+        if !rules.has_royal_king()
+            || problems.iter().any(|problem| {
+                matches!(
+                    problem,
+                    PositionProblem::MissingKing(_) | PositionProblem::ExtraKings(_)
+                )
+            })
+        {
+            return problems;
+        }
+        if self.clone().in_check(!self.turn) {
+            problems.push(PositionProblem::WaitingSideInCheck);
+        }
+        if self.checkers().len() > 2 {
+            problems.push(PositionProblem::TooManyCheckers);
+        }
+        problems
+    }
+    ///Tries to move a piece given a start and end position, returning the move in SAN. A pawn
+    ///reaching the last rank promotes to a queen.
+    pub fn move_piece(
+        &mut self,
+        old_position: Position,
+        new_position: Position,
+    ) -> Result<String, MoveError> {
+        self.promote_piece(old_position, new_position, PieceType::Queen)
+    }
+    ///Tries to move a piece given a start and end position, promoting a pawn that reaches the
+    ///last rank to the given piece, and returns the move in SAN.
+    pub fn promote_piece(
+        &mut self,
+        old_position: Position,
+        new_position: Position,
+        promotion: PieceType,
+    ) -> Result<String, MoveError> {
+        let disambiguation: String = self.disambiguation(self[old_position], new_position);
+        let mut notation: String = self.play(old_position, new_position, promotion)?;
+        if !notation.starts_with('O') {
+            notation.insert_str(1, &disambiguation);
+        }
+        Ok(notation)
+    }
+    ///Finds the move written in SAN, such as `Nbd7`, `O-O` or `e8=Q`, among the legal moves,
+    ///with the piece a pawn promotes to, which is a queen unless the move says otherwise.
+    pub fn parse_san(&self, san: &str) -> Option<(Position, Position, PieceType)> {
+        let san: &str = san.trim_end_matches(['+', '#', '!', '?']);
+        let is_short = match san {
+            "O-O" | "0-0" => Some(true),
+            "O-O-O" | "0-0-0" => Some(false),
+            _ => None,
+        };
+        if let Some(is_short) = is_short {
+            let king: Piece = self.get_king(self.turn)?;
+            let rook: Piece = self.castling_rook(king, is_short)?;
+            return Some((
+                king.position,
+                self.castling_destination(king, rook),
+                PieceType::Queen,
+            ));
+        }
+        // Promotions are written as `e8=Q`, though some write `e8Q`.
+        let (san, promotion): (&str, PieceType) = match san.char_indices().last() {
+            Some((index, letter)) if letter.is_ascii_uppercase() && index >= 2 => {
+                let promotion: PieceType =
+                    Piece::from_fen_char(letter, Position::default())?.piece_type;
+                (san[..index].trim_end_matches('='), promotion)
+            }
+            _ => (san, PieceType::Queen),
+        };
+        let mut chars: Vec<char> = san.chars().filter(|c| *c != 'x').collect();
+        let piece_type: PieceType = match chars.first()? {
+            'N' => PieceType::Knight,
+            'B' => PieceType::Bishop,
+            'R' => PieceType::Rook,
+            'Q' => PieceType::Queen,
+            'K' => PieceType::King,
+            _ => PieceType::Pawn,
+        };
+        if piece_type != PieceType::Pawn {
+            chars.remove(0);
+        }
+        if chars.len() < 2 {
+            return None;
+        }
+        let (hints, square) = chars.split_at(chars.len() - 2);
+        let new_position = Position::from_algebraic(&square.iter().collect::<String>())?;
+        let mut candidates = self
+            .get_all_color_pieces(self.turn)
+            .into_iter()
+            .filter(|piece| piece.piece_type == piece_type)
+            .filter(|piece| {
+                hints.iter().all(|hint| match hint {
+                    'a'..='h' => piece.position.x == *hint as u8 - b'a',
+                    '1'..='8' => piece.position.y == b'8' - *hint as u8,
+                    _ => false,
+                })
+            })
+            .filter(|piece| {
+                self.clone()
+                    .play(piece.position, new_position, promotion)
+                    .is_ok()
+            });
+        let old_position: Position = candidates.next()?.position;
+        if candidates.next().is_some() {
+            return None;
+        }
+        Some((old_position, new_position, promotion))
+    }
+    ///Finds the drop written in SAN, such as `N@f3`, or `@e4` for a pawn.
+    pub fn parse_drop(&self, san: &str) -> Option<(PieceType, Position)> {
+        let san: &str = san.trim_end_matches(['+', '#', '!', '?']);
+        let (piece, square) = san.split_once('@')?;
+        let piece_type: PieceType = match piece {
+            "" => PieceType::Pawn,
+            letter => {
+                let letter: char = letter.parse().ok()?;
+                Piece::from_fen_char(letter.to_ascii_uppercase(), Position::default())?.piece_type
+            }
+        };
+        Some((piece_type, Position::from_algebraic(square)?))
+    }
+    ///Plays a move or drop written in SAN, returning it as written by this board.
+    pub fn play_san(&mut self, san: &str) -> Option<String> {
+        if let Some((piece_type, position)) = self.parse_drop(san) {
+            return self.drop_piece(piece_type, position).ok();
+        }
+        let (old_position, new_position, promotion) = self.parse_san(san)?;
+        self.promote_piece(old_position, new_position, promotion)
+            .ok()
+    }
+    ///Drops a piece from the pocket of the side to move onto an empty square, returning the drop
+    ///in SAN, such as `N@f3`.
+    pub fn drop_piece(
+        &mut self,
+        piece_type: PieceType,
+        position: Position,
+    ) -> Result<String, MoveError> {
+        let color: Color = self.turn;
+        if self.pocket_count(color, piece_type) == 0 {
+            return Err(MoveError::NotInPocket);
+        }
+        if self[position].piece_type != PieceType::None {
+            return Err(MoveError::SquareOccupied);
+        }
+        if piece_type == PieceType::Pawn && (position.y == 0 || position.y == 7) {
+            return Err(MoveError::PawnDropOnBackRank);
+        }
+        let before: Self = self.clone();
+        let mut piece = Piece::new(piece_type, position, color);
+        // A dropped rook can't castle.
+        piece.has_moved = true;
+        self[position] = piece;
+        if self.in_check(color) {
+            *self = before;
+            return Err(MoveError::LeavesKingInCheck);
+        }
+        if let Some(count) = self.pocket_slot(color, piece_type) {
+            *count -= 1;
+        }
+        self.turn = !color;
+        self.last_piece_moved = Piece::new(PieceType::None, Position::new(0, 0), Color::Black);
+        self.last_move = Some((position, position));
+        self.last_promotion = None;
+        self.explosion = None;
+        self.previous_pieces = self.pieces;
+        self.update_move_counters(color, piece_type == PieceType::Pawn);
+
+        let mut notation: String = format!("{}@{position}", piece.fen_char().to_ascii_uppercase());
+        if self.in_check(self.turn) {
+            self.count_check(color);
+            notation.push(if self.is_checkmate(self.turn) {
+                '#'
+            } else {
+                '+'
+            });
+        }
+        let rules = self.variant.rules();
+        if let Err(error) = rules.check_move(&before, self) {
+            *self = before;
+            return Err(error);
+        }
+        Ok(rules.notation(self, notation))
+    }
+    ///Plays a move if the variant allows it and returns its SAN without telling apart pieces
+    ///that could make the same move.
+    fn play(
+        &mut self,
+        old_position: Position,
+        new_position: Position,
+        promotion: PieceType,
+    ) -> Result<String, MoveError> {
+        let before: Self = self.clone();
+        let notation: String = self.make_move(old_position, new_position, promotion)?;
+        let rules = self.variant.rules();
+        if let Err(error) = rules.check_move(&before, self) {
+            *self = before;
+            return Err(error);
+        }
+        Ok(rules.notation(self, notation))
+    }
+    ///Plays a move by the movement rules of standard chess and returns its SAN without telling
+    ///apart pieces that could make the same move.
+    fn make_move(
+        &mut self,
+        old_position: Position,
+        new_position: Position,
+        promotion: PieceType,
+    ) -> Result<String, MoveError> {
         self.previous_pieces = self.pieces;
 
         let moved_piece: Piece = self[old_position];
@@ -38,48 +753,512 @@ impl Board {
         let mut check_checkmate = "";
         let mut en_passant = false;
 
-        if moved_piece.piece_type == PieceType::None
-            || old_position == new_position
-            || self.turn != moved_piece.color
-            || (self.turn == attacked_piece.color && attacked_piece.piece_type != PieceType::None)
+        if moved_piece.piece_type == PieceType::None {
+            return Err(MoveError::NoPiece);
+        }
+        if self.turn != moved_piece.color {
+            return Err(MoveError::NotYourTurn);
+        }
+        if old_position == new_position {
+            return Err(MoveError::SameSquare);
+        }
+        // Moving the king onto its own rook is how castling is entered in Chess960.
+        if self.turn == attacked_piece.color
+            && attacked_piece.piece_type != PieceType::None
+            && !(moved_piece.piece_type == PieceType::King
+                && attacked_piece.piece_type == PieceType::Rook)
         {
-            return None;
+            return Err(MoveError::OwnPieceOnTarget);
+        }
+        let is_promotion: bool = moved_piece.piece_type == PieceType::Pawn
+            && new_position.y == Self::last_rank(moved_piece.color);
+        if is_promotion && !self.promotion_pieces().contains(&promotion) {
+            return Err(MoveError::InvalidPromotion);
         }
         match self.is_move_valid(moved_piece, attacked_piece) {
             MoveValidity::Valid => (),
             MoveValidity::EnPassant => en_passant = true,
-            MoveValidity::ShortCastle => return Some("O-O".to_string()),
-            MoveValidity::LongCastle => return Some("O-O-O".to_string()),
-            MoveValidity::Invalid => return None,
+            validity @ (MoveValidity::ShortCastle | MoveValidity::LongCastle) => {
+                self.last_move = Some((old_position, new_position));
+                self.last_promotion = None;
+                self.explosion = None;
+                self.update_move_counters(moved_piece.color, false);
+                let mut notation: String = if validity == MoveValidity::ShortCastle {
+                    "O-O".to_string()
+                } else {
+                    "O-O-O".to_string()
+                };
+                if self.in_check(self.turn) {
+                    self.count_check(moved_piece.color);
+                    notation.push(if self.is_checkmate(self.turn) {
+                        '#'
+                    } else {
+                        '+'
+                    });
+                }
+                return Ok(notation);
+            }
+            MoveValidity::Invalid(error) => return Err(error),
         }
 
         if attacked_piece.piece_type != PieceType::None {
             piece_captured = true;
         }
 
-        self.do_move(moved_piece, attacked_piece);
-        if self.in_check(self.turn) {
-            self.undo_move();
-            return None;
+        self.apply_move(moved_piece, attacked_piece, piece_captured || en_passant)?;
+        // The pawn may already be gone if its capture blew up in Atomic.
+        if is_promotion && self[new_position].piece_type == PieceType::Pawn {
+            self[new_position].piece_type = promotion;
+            self[new_position].promoted = true;
         }
+        self.last_promotion = is_promotion.then_some(promotion);
+        self.explosion = (piece_captured || en_passant)
+            .then_some(new_position)
+            .filter(|_| self.variant.rules().explodes_captures());
         self.turn = !self.turn;
         self.last_piece_moved = self[new_position];
+        self.last_move = Some((old_position, new_position));
         self.previous_pieces = self.pieces;
+        if (piece_captured || en_passant) && self.variant.rules().has_pockets() {
+            let captured_type: PieceType = if en_passant || attacked_piece.promoted {
+                PieceType::Pawn
+            } else {
+                attacked_piece.piece_type
+            };
+            if let Some(count) = self.pocket_slot(moved_piece.color, captured_type) {
+                *count += 1;
+            }
+        }
+        self.update_move_counters(
+            moved_piece.color,
+            piece_captured || en_passant || moved_piece.piece_type == PieceType::Pawn,
+        );
 
         if self.in_check(self.turn) {
+            self.count_check(moved_piece.color);
             check_checkmate = "+";
             if self.is_checkmate(self.turn) {
                 check_checkmate = "#";
             }
         }
-        Some(Board::make_piece_notation(
+        Ok(Board::make_piece_notation(
             moved_piece,
             attacked_piece,
-            piece_captured,
-            en_passant,
+            piece_captured || en_passant,
+            self.last_promotion,
             check_checkmate,
         ))
     }
+    ///Tries to play a move written in UCI notation, such as `e2e4`, `e7e8n` or `N@f3`.
+    pub fn move_uci(&mut self, uci: &str) -> Option<String> {
+        if let Some((piece_type, position)) = self.parse_drop(uci) {
+            return self.drop_piece(piece_type, position).ok();
+        }
+        let old_position = Position::from_algebraic(uci.get(0..2)?)?;
+        let new_position = Position::from_algebraic(uci.get(2..4)?)?;
+        let promotion: PieceType = match uci.get(4..) {
+            Some("") | None => PieceType::Queen,
+            Some(letter) => {
+                let letter: char = letter.parse().ok()?;
+                Piece::from_fen_char(letter, Position::default())?.piece_type
+            }
+        };
+        self.promote_piece(old_position, new_position, promotion)
+            .ok()
+    }
+    ///Returns the pieces a pawn can promote to, which include the king when it isn't royal.
+    pub fn promotion_pieces(&self) -> Vec<PieceType> {
+        let mut pieces: Vec<PieceType> = vec![
+            PieceType::Queen,
+            PieceType::Rook,
+            PieceType::Bishop,
+            PieceType::Knight,
+        ];
+        if !self.variant.rules().has_royal_king() {
+            pieces.push(PieceType::King);
+        }
+        pieces
+    }
+    ///Checks if moving the piece at one position to another is a legal pawn promotion, for
+    ///which a piece has to be chosen.
+    pub fn is_promotion(&self, old_position: Position, new_position: Position) -> bool {
+        let piece: Piece = self[old_position];
+        piece.piece_type == PieceType::Pawn
+            && piece.color == self.turn
+            && new_position.y == Self::last_rank(piece.color)
+            && self.clone().move_piece(old_position, new_position).is_ok()
+    }
+    ///Returns the rank a pawn of the given side promotes on.
+    fn last_rank(color: Color) -> u8 {
+        match color {
+            Color::White => 0,
+            Color::Black => 7,
+        }
+    }
+    ///Returns every legal move for the side to move as start and end positions.
+    pub fn legal_moves(&self) -> Vec<(Position, Position)> {
+        let mut moves: Vec<(Position, Position)> = Vec::new();
+        for piece in self.get_all_color_pieces(self.turn) {
+            for new_position in self.legal_destinations(piece.position) {
+                moves.push((piece.position, new_position));
+            }
+        }
+        moves
+    }
+    ///Returns every piece the side to move can drop from its pocket with the square it can go
+    ///to.
+    pub fn legal_drops(&self) -> Vec<(PieceType, Position)> {
+        let mut drops: Vec<(PieceType, Position)> = Vec::new();
+        for piece_type in POCKET_PIECES {
+            for position in self.legal_drop_squares(piece_type) {
+                drops.push((piece_type, position));
+            }
+        }
+        drops
+    }
+    ///Returns every square the side to move can drop a piece of the given type on.
+    pub fn legal_drop_squares(&self, piece_type: PieceType) -> Vec<Position> {
+        if self.pocket_count(self.turn, piece_type) == 0 {
+            return Vec::new();
+        }
+        let mut squares: Vec<Position> = Vec::new();
+        for y in 0u8..8u8 {
+            for x in 0u8..8u8 {
+                let position = Position::new(x, y);
+                if self.clone().drop_piece(piece_type, position).is_ok() {
+                    squares.push(position);
+                }
+            }
+        }
+        squares
+    }
+    ///Returns every square the piece at the given position can legally move to.
+    pub fn legal_destinations(&self, old_position: Position) -> Vec<Position> {
+        let mut destinations: Vec<Position> = Vec::new();
+        for y in 0u8..8u8 {
+            for x in 0u8..8u8 {
+                let new_position = Position::new(x, y);
+                let Ok(notation) = self
+                    .clone()
+                    .play(old_position, new_position, PieceType::Queen)
+                else {
+                    continue;
+                };
+                // Castling can be entered two ways, but only the usual one for the game counts.
+                if notation.starts_with('O') {
+                    let king: Piece = self[old_position];
+                    let is_short: bool = !notation.starts_with("O-O-O");
+                    if self
+                        .castling_rook(king, is_short)
+                        .is_some_and(|rook| self.castling_destination(king, rook) != new_position)
+                    {
+                        continue;
+                    }
+                }
+                destinations.push(new_position);
+            }
+        }
+        destinations
+    }
+    ///Returns the outcome of the game if it has ended by the variant's rules or the side to move
+    ///has no legal moves or drops.
+    pub fn outcome(&self) -> Option<Outcome> {
+        let rules = self.variant.rules();
+        if let Some(outcome) = rules.outcome(self) {
+            return Some(outcome);
+        }
+        if !self.legal_moves().is_empty() || !self.legal_drops().is_empty() {
+            return None;
+        }
+        Some(rules.outcome_without_moves(self))
+    }
+    ///Returns the outcome when the given side runs out of time.
+    pub fn timeout_outcome(&self, flagged_color: Color) -> Outcome {
+        if self.has_insufficient_material(!flagged_color) {
+            return Outcome::TimeoutVsInsufficientMaterial;
+        }
+        Outcome::Timeout(!flagged_color)
+    }
+    ///Checks if the given side has too little material to ever checkmate.
+    pub fn has_insufficient_material(&self, color: Color) -> bool {
+        let pieces: Vec<Piece> = self.get_all_color_pieces(color);
+        let minor_pieces = pieces
+            .iter()
+            .filter(|piece| matches!(piece.piece_type, PieceType::Knight | PieceType::Bishop))
+            .count();
+        // The side may have no king, as in Horde.
+        let king_count = pieces
+            .iter()
+            .filter(|piece| piece.piece_type == PieceType::King)
+            .count();
+        pieces.len() - minor_pieces == king_count
+            && minor_pieces <= 1
+            && POCKET_PIECES
+                .iter()
+                .all(|piece_type| self.pocket_count(color, *piece_type) == 0)
+    }
+    ///Checks if the side to move is in check.
+    pub fn is_check(&self) -> bool {
+        self.clone().in_check(self.turn)
+    }
+    ///Returns the start and end positions of the last move played on this board.
+    pub fn last_move(&self) -> Option<(Position, Position)> {
+        self.last_move
+    }
+    ///Returns the last move played on this board in UCI notation, as read by `move_uci`.
+    pub fn last_move_uci(&self) -> Option<String> {
+        let (old_position, new_position) = self.last_move?;
+        if old_position == new_position {
+            let letter: char = self[new_position].fen_char().to_ascii_uppercase();
+            return Some(format!("{letter}@{new_position}"));
+        }
+        let promotion: String = self
+            .last_promotion
+            .map(|piece_type| {
+                Piece::new(piece_type, new_position, Color::Black)
+                    .fen_char()
+                    .to_string()
+            })
+            .unwrap_or_default();
+        Some(format!("{old_position}{new_position}{promotion}"))
+    }
+    ///Returns the position of the side to move's king if it is in check.
+    pub fn checked_king(&self) -> Option<Position> {
+        if !self.is_check() {
+            return None;
+        }
+        self.get_king(self.turn).map(|king| king.position)
+    }
+    ///Returns the position in Forsyth-Edwards Notation.
+    pub fn to_fen(&self) -> String {
+        let mut placement = String::new();
+        for y in 0u8..8u8 {
+            let mut empty_squares = 0;
+            for x in 0u8..8u8 {
+                let piece = self[Position::new(x, y)];
+                if piece.piece_type == PieceType::None {
+                    empty_squares += 1;
+                    continue;
+                }
+                if empty_squares > 0 {
+                    placement.push_str(&empty_squares.to_string());
+                    empty_squares = 0;
+                }
+                placement.push(piece.fen_char());
+                if piece.promoted && self.variant.rules().has_pockets() {
+                    placement.push('~');
+                }
+            }
+            if empty_squares > 0 {
+                placement.push_str(&empty_squares.to_string());
+            }
+            if y < 7 {
+                placement.push('/');
+            }
+        }
+        if self.variant.rules().has_pockets() {
+            placement.push('[');
+            for color in [Color::White, Color::Black] {
+                for piece_type in POCKET_PIECES {
+                    let letter: char =
+                        Piece::new(piece_type, Position::default(), color).fen_char();
+                    for _ in 0..self.pocket_count(color, piece_type) {
+                        placement.push(letter);
+                    }
+                }
+            }
+            placement.push(']');
+        }
+        let en_passant = match self.en_passant_pawn() {
+            Some(pawn) if pawn.color == Color::White => {
+                Position::new(pawn.position.x, 5).to_string()
+            }
+            Some(pawn) => Position::new(pawn.position.x, 2).to_string(),
+            None => "-".to_string(),
+        };
+        let mut fen = format!(
+            "{} {} {} {} {} {}",
+            placement,
+            if self.turn == Color::White { "w" } else { "b" },
+            self.castling_rights(),
+            en_passant,
+            self.halfmove_clock,
+            self.fullmove_number,
+        );
+        if self.variant == VariantKind::ThreeCheck {
+            fen.push_str(&format!(" +{}+{}", self.checks[0], self.checks[1]));
+        }
+        fen
+    }
+    ///Returns how many times the given side has given check.
+    pub fn checks_given(&self, color: Color) -> u8 {
+        self.checks[usize::from(color == Color::Black)]
+    }
+    ///Adds a check given by the given side.
+    fn count_check(&mut self, color: Color) {
+        self.checks[usize::from(color == Color::Black)] += 1;
+    }
+    ///Returns how many pieces the given side has on the board.
+    pub fn piece_count(&self, color: Color) -> usize {
+        self.get_all_color_pieces(color).len()
+    }
+    ///Checks if the side to move has a piece that can capture, going by how the pieces move
+    ///alone.
+    pub fn has_capture(&self) -> bool {
+        !self.capturing_pieces().is_empty()
+    }
+    ///Returns the squares of the side to move's pieces that can capture, going by how the pieces
+    ///move alone.
+    pub fn capturing_pieces(&self) -> Vec<Position> {
+        let targets: Vec<Piece> = self.get_all_color_pieces(!self.turn);
+        // Only a pawn captures by moving to the empty square behind a pawn that moved two.
+        let en_passant_square: Option<Piece> = self.en_passant_pawn().map(|pawn| {
+            let behind_pawn: u8 = if pawn.color == Color::White { 5 } else { 2 };
+            self[Position::new(pawn.position.x, behind_pawn)]
+        });
+        self.get_all_color_pieces(self.turn)
+            .into_iter()
+            .filter(|piece| {
+                targets.iter().any(|target| {
+                    self.clone().is_move_valid(*piece, *target) == MoveValidity::Valid
+                }) || en_passant_square.is_some_and(|square| {
+                    piece.piece_type == PieceType::Pawn
+                        && self.clone().is_move_valid(*piece, square) == MoveValidity::EnPassant
+                })
+            })
+            .map(|piece| piece.position)
+            .collect()
+    }
+    ///Returns the square the last move's capture blew up, in Atomic.
+    pub fn last_explosion(&self) -> Option<Position> {
+        self.explosion
+    }
+    ///Returns how many pieces of the given type the given side holds in its pocket.
+    pub fn pocket_count(&self, color: Color, piece_type: PieceType) -> u8 {
+        POCKET_PIECES
+            .iter()
+            .position(|pocket_piece| *pocket_piece == piece_type)
+            .map_or(0, |index| {
+                self.pockets[usize::from(color == Color::Black)][index]
+            })
+    }
+    ///Returns the count of pieces of the given type in the given side's pocket to change, if
+    ///that type can be held.
+    fn pocket_slot(&mut self, color: Color, piece_type: PieceType) -> Option<&mut u8> {
+        let index = POCKET_PIECES
+            .iter()
+            .position(|pocket_piece| *pocket_piece == piece_type)?;
+        Some(&mut self.pockets[usize::from(color == Color::Black)][index])
+    }
+    ///Returns the square of the given side's king, if it has one.
+    pub fn king_position(&self, color: Color) -> Option<Position> {
+        self.get_king(color).map(|king| king.position)
+    }
+    ///Returns the pawn that just moved two squares and can be captured en passant, if any.
+    fn en_passant_pawn(&self) -> Option<Piece> {
+        let pawn: Piece = self.last_piece_moved;
+        let double_step_rank = match pawn.color {
+            Color::White => 4,
+            Color::Black => 3,
+        };
+        // A board read from FEN has no last move, so the FEN's en passant square is trusted.
+        let is_double_step = self
+            .last_move
+            .is_none_or(|(from, to)| from.y.abs_diff(to.y) == 2);
+        (pawn.piece_type == PieceType::Pawn
+            && pawn.position.y == double_step_rank
+            && pawn.color != self.turn
+            && is_double_step)
+            .then_some(pawn)
+    }
+    ///Returns the castling rights in X-FEN, based on which kings and rooks haven't moved. A right
+    ///is written as the rook's file if another rook is further out on the same side.
+    fn castling_rights(&self) -> String {
+        let mut rights = String::new();
+        for color in [Color::White, Color::Black] {
+            let Some(king) = self.get_king(color) else {
+                continue;
+            };
+            for (is_short, letter) in [(true, 'K'), (false, 'Q')] {
+                let Some(rook) = self.castling_rook(king, is_short) else {
+                    continue;
+                };
+                let mut files: Vec<u8> = (0..king.position.x).collect();
+                if is_short {
+                    files = (king.position.x + 1..8).rev().collect();
+                }
+                let outermost_rook: Option<u8> = files.into_iter().find(|x| {
+                    let piece: Piece = self[Position::new(*x, rook.position.y)];
+                    piece.piece_type == PieceType::Rook && piece.color == color
+                });
+                let letter: char = if outermost_rook == Some(rook.position.x) {
+                    letter
+                } else {
+                    (b'A' + rook.position.x) as char
+                };
+                rights.push(match color {
+                    Color::White => letter,
+                    Color::Black => letter.to_ascii_lowercase(),
+                });
+            }
+        }
+        if rights.is_empty() {
+            rights.push('-');
+        }
+        rights
+    }
+    ///Advances the halfmove clock and fullmove number after a move by the given side.
+    fn update_move_counters(&mut self, moved_color: Color, reset_halfmove_clock: bool) {
+        if reset_halfmove_clock {
+            self.halfmove_clock = 0;
+        } else {
+            self.halfmove_clock += 1;
+        }
+        if moved_color == Color::Black {
+            self.fullmove_number += 1;
+        }
+    }
+    ///Moves a piece, blowing up the pieces around a capture if the variant has explosions, and
+    ///takes the move back if it leaves the mover's king in check or blown up.
+    fn apply_move(
+        &mut self,
+        moved_piece: Piece,
+        attacked_piece: Piece,
+        is_capture: bool,
+    ) -> Result<(), MoveError> {
+        self.do_move(moved_piece, attacked_piece);
+        if is_capture && self.variant.rules().explodes_captures() {
+            self.explode(attacked_piece.position);
+            if self.get_king(moved_piece.color).is_none() {
+                self.undo_move();
+                return Err(MoveError::ExplodesOwnKing);
+            }
+        }
+        if self.in_check(moved_piece.color) {
+            self.undo_move();
+            return Err(MoveError::LeavesKingInCheck);
+        }
+        Ok(())
+    }
+    ///Removes the piece on the given square and every piece but a pawn next to it, as a capture
+    ///does in Atomic.
+    fn explode(&mut self, center: Position) {
+        for position in Self::surrounding_squares(center) {
+            if position == center || self[position].piece_type != PieceType::Pawn {
+                self[position] = Piece::new(PieceType::None, position, Color::White);
+            }
+        }
+    }
+    ///Returns the given square and the squares next to it that are on the board.
+    pub fn surrounding_squares(center: Position) -> Vec<Position> {
+        let mut squares: Vec<Position> = Vec::new();
+        for y in center.y.saturating_sub(1)..=(center.y + 1).min(7) {
+            for x in center.x.saturating_sub(1)..=(center.x + 1).min(7) {
+                squares.push(Position::new(x, y));
+            }
+        }
+        squares
+    }
     ///Moves a piece to a specified location.
     fn do_move(&mut self, moved_piece: Piece, attacked_piece: Piece) {
         self[attacked_piece.position] = Piece {
@@ -87,10 +1266,19 @@ impl Board {
             position: attacked_piece.position,
             color: moved_piece.color,
             has_moved: true,
+            promoted: moved_piece.promoted,
         };
         self[moved_piece.position] =
             Piece::new(PieceType::None, moved_piece.position, Color::White);
     }
+    ///Checks if a pawn can move two squares, which it can from its side's second rank, or from
+    ///White's first rank in Horde.
+    fn can_double_step(pawn: Piece) -> bool {
+        match pawn.color {
+            Color::White => pawn.position.y >= 6,
+            Color::Black => pawn.position.y == 1,
+        }
+    }
     ///Sets the board back to the last move.
     fn undo_move(&mut self) {
         self.pieces = self.previous_pieces;
@@ -107,7 +1295,7 @@ impl Board {
         //WHITE PAWN
         if moved_piece.piece_type == PieceType::Pawn && moved_piece.color == Color::White {
             if (y_move == -1 && x_move == 0 && attacked_piece.piece_type == PieceType::None)
-                || (!moved_piece.has_moved
+                || (Self::can_double_step(moved_piece)
                     && y_move == -2
                     && x_move == 0
                     && attacked_piece.piece_type == PieceType::None)
@@ -116,11 +1304,10 @@ impl Board {
                     && attacked_piece.piece_type != PieceType::None)
             {
                 if self.is_jumping_vertically(moved_piece, y_move) {
-                    return MoveValidity::Invalid;
+                    return MoveValidity::Invalid(MoveError::PathBlocked);
                 }
                 return MoveValidity::Valid;
-            } else if self.last_piece_moved.piece_type == PieceType::Pawn
-                && self.last_piece_moved.position.y == 3
+            } else if self.en_passant_pawn() == Some(self.last_piece_moved)
                 && x_abs == 1
                 && y_move == -1
                 && self.last_piece_moved.position
@@ -134,7 +1321,7 @@ impl Board {
                 self.do_move(moved_piece, attacked_piece);
                 if self.in_check(self.turn) {
                     self.undo_move();
-                    return MoveValidity::Invalid;
+                    return MoveValidity::Invalid(MoveError::LeavesKingInCheck);
                 }
                 self.last_piece_moved = self[attacked_piece.position];
                 return MoveValidity::EnPassant;
@@ -144,7 +1331,7 @@ impl Board {
         if moved_piece.piece_type == PieceType::Pawn && moved_piece.color == Color::Black {
             #[allow(clippy::collapsible_if)]
             if (y_move == 1 && x_move == 0 && attacked_piece.piece_type == PieceType::None)
-                || (!moved_piece.has_moved
+                || (Self::can_double_step(moved_piece)
                     && y_move == 2
                     && x_move == 0
                     && attacked_piece.piece_type == PieceType::None)
@@ -153,11 +1340,10 @@ impl Board {
                     && attacked_piece.piece_type != PieceType::None)
             {
                 if self.is_jumping_vertically(moved_piece, y_move) {
-                    return MoveValidity::Invalid;
+                    return MoveValidity::Invalid(MoveError::PathBlocked);
                 }
                 return MoveValidity::Valid;
-            } else if self.last_piece_moved.piece_type == PieceType::Pawn
-                && self.last_piece_moved.position.y == 4
+            } else if self.en_passant_pawn() == Some(self.last_piece_moved)
                 && x_abs == 1
                 && y_move == 1
             {
@@ -173,7 +1359,7 @@ impl Board {
 
                     if self.in_check(self.turn) {
                         self.undo_move();
-                        return MoveValidity::Invalid;
+                        return MoveValidity::Invalid(MoveError::LeavesKingInCheck);
                     }
                     self.last_piece_moved = self[attacked_piece.position];
                     return MoveValidity::EnPassant;
@@ -184,12 +1370,12 @@ impl Board {
         if moved_piece.piece_type == PieceType::Rook {
             if moved_piece.position.x == attacked_piece.position.x {
                 if self.is_jumping_vertically(moved_piece, y_move) {
-                    return MoveValidity::Invalid;
+                    return MoveValidity::Invalid(MoveError::PathBlocked);
                 }
                 return MoveValidity::Valid;
             } else if moved_piece.position.y == attacked_piece.position.y {
                 if self.is_jumping_horizontally(moved_piece, x_move) {
-                    return MoveValidity::Invalid;
+                    return MoveValidity::Invalid(MoveError::PathBlocked);
                 }
                 return MoveValidity::Valid;
             }
@@ -204,12 +1390,12 @@ impl Board {
         if moved_piece.piece_type == PieceType::Bishop {
             if (x_move + y_move) == 0i8 {
                 if self.is_jumping_diagonally_pos(moved_piece, x_move) {
-                    return MoveValidity::Invalid;
+                    return MoveValidity::Invalid(MoveError::PathBlocked);
                 }
                 return MoveValidity::Valid;
             } else if (x_move - y_move) == 0i8 {
                 if self.is_jumping_diagonally_neg(moved_piece, x_move) {
-                    return MoveValidity::Invalid;
+                    return MoveValidity::Invalid(MoveError::PathBlocked);
                 }
                 return MoveValidity::Valid;
             }
@@ -218,169 +1404,138 @@ impl Board {
         if moved_piece.piece_type == PieceType::Queen {
             if moved_piece.position.x == attacked_piece.position.x {
                 if self.is_jumping_vertically(moved_piece, y_move) {
-                    return MoveValidity::Invalid;
+                    return MoveValidity::Invalid(MoveError::PathBlocked);
                 }
                 return MoveValidity::Valid;
             } else if moved_piece.position.y == attacked_piece.position.y {
                 if self.is_jumping_horizontally(moved_piece, x_move) {
-                    return MoveValidity::Invalid;
+                    return MoveValidity::Invalid(MoveError::PathBlocked);
                 }
                 return MoveValidity::Valid;
             } else if (x_move + y_move) == 0i8 {
                 if self.is_jumping_diagonally_pos(moved_piece, x_move) {
-                    return MoveValidity::Invalid;
+                    return MoveValidity::Invalid(MoveError::PathBlocked);
                 }
                 return MoveValidity::Valid;
             } else if (x_move - y_move) == 0i8 {
                 if self.is_jumping_diagonally_neg(moved_piece, x_move) {
-                    return MoveValidity::Invalid;
+                    return MoveValidity::Invalid(MoveError::PathBlocked);
                 }
                 return MoveValidity::Valid;
             }
         }
-        //WHITE KING
-        if moved_piece.piece_type == PieceType::King && moved_piece.color == Color::White {
-            if (x_abs == 0 && y_abs == 1)
-                || (x_abs == 1 && y_abs == 0)
-                || (x_abs == 1 && y_abs == 1)
-            {
-                return MoveValidity::Valid;
-            } else if x_move == 2
-                && y_move == 0
-                && !moved_piece.has_moved
-                && self[Position::new(7, 7)].piece_type == PieceType::Rook
-                && !self[Position::new(7, 7)].has_moved
-                && !self.is_jumping_horizontally(moved_piece, x_move)
-                && !self.in_check(Color::White)
-            {
-                if self.short_castling_checks(true) {
-                    return MoveValidity::Invalid;
-                }
-                return MoveValidity::ShortCastle;
-            } else if x_move == -3
-                && y_move == 0
-                && !moved_piece.has_moved
-                && self[Position::new(0, 7)].piece_type == PieceType::Rook
-                && !self[Position::new(0, 7)].has_moved
-                && !self.is_jumping_horizontally(moved_piece, x_move)
-                && !self.in_check(Color::White)
-            {
-                if self.long_castling_checks(true) {
-                    return MoveValidity::Invalid;
-                }
-                return MoveValidity::LongCastle;
-            }
-        }
-        //BLACK KING
-        if moved_piece.piece_type == PieceType::King && moved_piece.color == Color::Black {
-            if (x_abs == 0 && y_abs == 1)
-                || (x_abs == 1 && y_abs == 0)
-                || (x_abs == 1 && y_abs == 1)
+        //KING
+        if moved_piece.piece_type == PieceType::King {
+            if attacked_piece.piece_type == PieceType::Rook
+                && attacked_piece.color == moved_piece.color
             {
+                return self.castling_validity(moved_piece, attacked_piece);
+            } else if x_abs <= 1 && y_abs <= 1 {
                 return MoveValidity::Valid;
-            } else if x_move == 2
-                && y_move == 0
-                && !moved_piece.has_moved
-                && self[Position::new(7, 0)].piece_type == PieceType::Rook
-                && !self[Position::new(7, 0)].has_moved
-                && !self.is_jumping_horizontally(moved_piece, x_move)
-                && !self.in_check(Color::Black)
-            {
-                if self.short_castling_checks(false) {
-                    return MoveValidity::Invalid;
-                }
-                return MoveValidity::ShortCastle;
-            } else if x_move == -3
-                && y_move == 0
-                && !moved_piece.has_moved
-                && self[Position::new(0, 0)].piece_type == PieceType::Rook
-                && !self[Position::new(0, 0)].has_moved
-                && !self.is_jumping_horizontally(moved_piece, x_move)
-                && !self.in_check(Color::Black)
-            {
-                if self.long_castling_checks(false) {
-                    return MoveValidity::Invalid;
-                }
-                return MoveValidity::LongCastle;
+            } else if x_abs >= 2 && y_move == 0 && matches!(attacked_piece.position.x, 2 | 6) {
+                return match self.castling_rook(moved_piece, attacked_piece.position.x == 6) {
+                    Some(rook) => self.castling_validity(moved_piece, rook),
+                    None => MoveValidity::Invalid(MoveError::NoCastlingRights),
+                };
             }
         }
-        MoveValidity::Invalid
+        MoveValidity::Invalid(MoveError::InvalidMovement(moved_piece.piece_type))
     }
-    ///Attempts to castle short, and moves the pieces if successful.
-    fn short_castling_checks(&mut self, is_white: bool) -> bool {
-        if is_white {
-            for x in 4..6 {
-                self.do_move(
-                    Piece::new(PieceType::King, Position::new(x, 7), Color::White),
-                    Piece::new(PieceType::None, Position::new(x + 1, 7), Color::White),
-                );
-                if self.in_check(Color::White) {
-                    self.undo_move();
-                    return true;
-                }
-            }
-            self.do_move(
-                Piece::new(PieceType::Rook, Position::new(7, 7), Color::White),
-                Piece::new(PieceType::None, Position::new(5, 7), Color::White),
-            );
-            self.last_piece_moved = self[Position::new(6, 7)];
+    ///Checks if the king can castle with the given rook, and moves both pieces if it can. The
+    ///king ends on the g or c file with the rook beside it, wherever they started.
+    fn castling_validity(&mut self, king: Piece, rook: Piece) -> MoveValidity {
+        let back_rank: u8 = if king.color == Color::White { 7 } else { 0 };
+        if king.has_moved
+            || king.position.y != back_rank
+            || rook.has_moved
+            || rook.position.y != back_rank
+        {
+            return MoveValidity::Invalid(MoveError::NoCastlingRights);
+        }
+        let is_short: bool = rook.position.x > king.position.x;
+        let (king_x, rook_x): (u8, u8) = if is_short { (6, 5) } else { (2, 3) };
+        let first_x: u8 = king.position.x.min(rook.position.x).min(king_x).min(rook_x);
+        let last_x: u8 = king.position.x.max(rook.position.x).max(king_x).max(rook_x);
+        // Every square either piece crosses must be empty, apart from the king and rook.
+        if (first_x..=last_x).any(|x| {
+            x != king.position.x
+                && x != rook.position.x
+                && self[Position::new(x, back_rank)].piece_type != PieceType::None
+        }) {
+            return MoveValidity::Invalid(MoveError::PathBlocked);
+        }
+        if self.in_check(king.color) {
+            return MoveValidity::Invalid(MoveError::CastlingOutOfCheck);
+        }
+        let pieces: [Piece; 64] = self.pieces;
+        let king_path: Vec<u8> = if king_x > king.position.x {
+            (king.position.x + 1..=king_x).collect()
         } else {
-            for x in 4..6 {
-                self.do_move(
-                    Piece::new(PieceType::King, Position::new(x, 0), Color::Black),
-                    Piece::new(PieceType::None, Position::new(x + 1, 0), Color::Black),
-                );
-                if self.in_check(Color::Black) {
-                    self.undo_move();
-                    return true;
-                }
-            }
+            (king_x..king.position.x).rev().collect()
+        };
+        for x in king_path {
             self.do_move(
-                Piece::new(PieceType::Rook, Position::new(7, 0), Color::Black),
-                Piece::new(PieceType::None, Position::new(5, 0), Color::Black),
+                king,
+                Piece::new(PieceType::None, Position::new(x, back_rank), king.color),
             );
-            self.last_piece_moved = self[Position::new(6, 0)];
+            let is_attacked: bool = self.in_check(king.color);
+            self.pieces = pieces;
+            if is_attacked {
+                return MoveValidity::Invalid(MoveError::CastlingThroughCheck);
+            }
+        }
+        for piece in [king, rook] {
+            self[piece.position] = Piece::new(PieceType::None, piece.position, Color::White);
         }
+        for (piece, x) in [(king, king_x), (rook, rook_x)] {
+            self[Position::new(x, back_rank)] = Piece {
+                position: Position::new(x, back_rank),
+                has_moved: true,
+                ..piece
+            };
+        }
+        // The rook may have moved over the king's path, so the final position is checked too.
+        if self.in_check(king.color) {
+            self.pieces = pieces;
+            return MoveValidity::Invalid(MoveError::CastlingThroughCheck);
+        }
+        self.last_piece_moved = self[Position::new(king_x, back_rank)];
         self.turn = !self.turn;
-        false
-    }
-    ///Attempts to castle long, and moves the pieces if successful.
-    fn long_castling_checks(&mut self, is_white: bool) -> bool {
-        if is_white {
-            for x in (2..5).rev() {
-                self.do_move(
-                    Piece::new(PieceType::King, Position::new(x, 7), Color::White),
-                    Piece::new(PieceType::None, Position::new(x - 1, 7), Color::White),
-                );
-                if self.in_check(Color::White) {
-                    self.undo_move();
-                    return true;
-                }
-            }
-            self.do_move(
-                Piece::new(PieceType::Rook, Position::new(0, 7), Color::White),
-                Piece::new(PieceType::None, Position::new(2, 7), Color::White),
-            );
-            self.last_piece_moved = self[Position::new(1, 7)];
+        if is_short {
+            MoveValidity::ShortCastle
         } else {
-            for x in (2..5).rev() {
-                self.do_move(
-                    Piece::new(PieceType::King, Position::new(x, 0), Color::Black),
-                    Piece::new(PieceType::None, Position::new(x - 1, 0), Color::Black),
-                );
-                if self.in_check(Color::Black) {
-                    self.undo_move();
-                    return true;
-                }
-            }
-            self.do_move(
-                Piece::new(PieceType::Rook, Position::new(0, 0), Color::Black),
-                Piece::new(PieceType::None, Position::new(2, 0), Color::Black),
-            );
-            self.last_piece_moved = self[Position::new(1, 0)];
+            MoveValidity::LongCastle
         }
-        self.turn = !self.turn;
-        false
+    }
+    ///Returns the unmoved rook the king could castle with on the given side, taking the outermost
+    ///one if there are several.
+    fn castling_rook(&self, king: Piece, is_short: bool) -> Option<Piece> {
+        if king.has_moved {
+            return None;
+        }
+        let mut files: Vec<u8> = (0..king.position.x).collect();
+        if is_short {
+            files = (king.position.x + 1..8).rev().collect();
+        }
+        files
+            .into_iter()
+            .map(|x| self[Position::new(x, king.position.y)])
+            .find(|piece| {
+                piece.piece_type == PieceType::Rook && piece.color == king.color && !piece.has_moved
+            })
+    }
+    ///Returns the square the king is moved to when castling with the given rook: the rook's
+    ///square in Chess960, or the king's landing square otherwise.
+    fn castling_destination(&self, king: Piece, rook: Piece) -> Position {
+        if self.variant.rules().castles_onto_rook() {
+            return rook.position;
+        }
+        let king_x: u8 = if rook.position.x > king.position.x {
+            6
+        } else {
+            2
+        };
+        Position::new(king_x, king.position.y)
     }
     ///Checks if a specified move jumps over another piece horizontally.
     fn is_jumping_horizontally(&self, piece: Piece, x_move: i8) -> bool {
@@ -472,8 +1627,23 @@ impl Board {
     }
     ///Checks if the given side is in check.
     fn in_check(&mut self, king_color: Color) -> bool {
+        let rules = self.variant.rules();
+        if !rules.has_royal_king() {
+            return false;
+        }
         let attacking_pieces: Vec<Piece> = self.get_all_color_pieces(!king_color);
-        let defending_king: Piece = self.get_king(king_color);
+        let Some(defending_king) = self.get_king(king_color) else {
+            return false;
+        };
+        // Capturing a king next to the other one would blow up both, and a side whose king blew
+        // up has already lost, so neither king is in check then.
+        if rules.explodes_captures()
+            && self.get_king(!king_color).is_none_or(|king| {
+                Self::surrounding_squares(king.position).contains(&defending_king.position)
+            })
+        {
+            return false;
+        }
         for piece in attacking_pieces {
             if self.is_move_valid(piece, defending_king) == MoveValidity::Valid {
                 return true;
@@ -489,13 +1659,23 @@ impl Board {
             .copied()
             .collect()
     }
-    ///Returns the king of the specified color/side.
-    fn get_king(&self, king_color: Color) -> Piece {
-        *self
-            .pieces
+    ///Returns the king of the specified color/side, if it has one.
+    fn get_king(&self, king_color: Color) -> Option<Piece> {
+        self.pieces
             .iter()
             .find(|piece| piece.color == king_color && piece.piece_type == PieceType::King)
-            .unwrap()
+            .copied()
+    }
+    ///Returns the pieces giving check to the side to move.
+    fn checkers(&self) -> Vec<Piece> {
+        let mut board: Self = self.clone();
+        let Some(king) = board.get_king(self.turn) else {
+            return Vec::new();
+        };
+        self.get_all_color_pieces(!self.turn)
+            .into_iter()
+            .filter(|piece| board.is_move_valid(*piece, king) == MoveValidity::Valid)
+            .collect()
     }
     ///Checks if the side in check is in checkmate.
     fn is_checkmate(&mut self, color: Color) -> bool {
@@ -508,9 +1688,11 @@ impl Board {
                         && !(self.turn == attacked_piece.color
                             && attacked_piece.piece_type != PieceType::None)
                     {
-                        self.do_move(*moved_piece, attacked_piece);
-                        if self.in_check(color) {
-                            self.undo_move();
+                        let is_capture = attacked_piece.piece_type != PieceType::None;
+                        if self
+                            .apply_move(*moved_piece, attacked_piece, is_capture)
+                            .is_err()
+                        {
                             continue;
                         }
                         self.undo_move();
@@ -521,12 +1703,45 @@ impl Board {
         }
         true
     }
+    ///Returns the file, rank or square needed in SAN to tell the moved piece apart from others
+    ///of the same type that could move to the same square.
+    fn disambiguation(&self, moved_piece: Piece, new_position: Position) -> String {
+        if matches!(
+            moved_piece.piece_type,
+            PieceType::None | PieceType::Pawn | PieceType::King
+        ) {
+            return String::new();
+        }
+        let rivals: Vec<Position> = self
+            .get_all_color_pieces(moved_piece.color)
+            .into_iter()
+            .filter(|piece| {
+                piece.piece_type == moved_piece.piece_type
+                    && piece.position != moved_piece.position
+                    && self
+                        .clone()
+                        .play(piece.position, new_position, PieceType::Queen)
+                        .is_ok()
+            })
+            .map(|piece| piece.position)
+            .collect();
+        let square: String = moved_piece.position.to_string();
+        if rivals.is_empty() {
+            String::new()
+        } else if rivals.iter().all(|rival| rival.x != moved_piece.position.x) {
+            square[0..1].to_string()
+        } else if rivals.iter().all(|rival| rival.y != moved_piece.position.y) {
+            square[1..2].to_string()
+        } else {
+            square
+        }
+    }
     ///Returns the piece notation for the played move.
     fn make_piece_notation(
         moved_piece: Piece,
         attacked_piece: Piece,
         piece_captured: bool,
-        en_passant: bool,
+        promotion: Option<PieceType>,
         check_checkmate: &str,
     ) -> String {
         let mut piece: String = match moved_piece.piece_type {
@@ -540,13 +1755,16 @@ impl Board {
         if moved_piece.piece_type == PieceType::Pawn && piece_captured {
             piece = ((moved_piece.position.x + 97) as char).to_string();
         }
+        let promotion: String = promotion.map_or(String::new(), |promotion| {
+            let letter: char = Piece::new(promotion, Position::default(), Color::White).fen_char();
+            format!("={letter}")
+        });
         format!(
-            "{}{}{}{}{}{}",
+            "{}{}{}{}{}",
             piece,
             if piece_captured { "x" } else { "" },
-            (attacked_piece.position.x + 97) as char,
-            8 - attacked_piece.position.y,
-            if en_passant { " e.p." } else { "" },
+            attacked_piece.position,
+            promotion,
             check_checkmate,
         )
     }
@@ -565,3 +1783,264 @@ impl IndexMut<Position> for Board {
         &mut self.pieces[(8 * position.y + position.x) as usize]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pawn_promotes_to_chosen_piece() {
+        let mut board = Board::from_fen("4k3/P7/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        let a7 = Position::from_algebraic("a7").unwrap();
+        let a8 = Position::from_algebraic("a8").unwrap();
+        assert!(board.is_promotion(a7, a8));
+        assert_eq!(board.move_piece(a7, a8).unwrap(), "a8=Q+");
+        assert_eq!(board[a8].piece_type, PieceType::Queen);
+        assert!(board[a8].promoted);
+        assert!(Board::from_fen(&board.to_fen()).is_ok());
+
+        let mut board = Board::from_fen("4k3/P7/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(board.play_san("a8=N").as_deref(), Some("a8=N"));
+        assert_eq!(board[a8].piece_type, PieceType::Knight);
+    }
+
+    #[test]
+    fn pawn_cannot_promote_to_king_or_pawn() {
+        let board = Board::from_fen("4k3/P7/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        let a7 = Position::from_algebraic("a7").unwrap();
+        let a8 = Position::from_algebraic("a8").unwrap();
+        for piece_type in [PieceType::King, PieceType::Pawn] {
+            assert_eq!(
+                board.clone().promote_piece(a7, a8, piece_type),
+                Err(MoveError::InvalidPromotion)
+            );
+        }
+    }
+
+    #[test]
+    fn antichess_pawn_can_promote_to_king() {
+        let mut board =
+            Board::from_variant_fen("8/P7/8/8/8/8/8/7n w - - 0 1", VariantKind::Antichess).unwrap();
+        assert_eq!(board.play_san("a8=K").as_deref(), Some("a8=K"));
+        assert_eq!(
+            board[Position::from_algebraic("a8").unwrap()].piece_type,
+            PieceType::King
+        );
+    }
+
+    #[test]
+    fn en_passant_is_written_as_a_pawn_capture() {
+        let mut board = Board::from_fen("4k3/3p4/8/4P3/8/8/8/4K3 b - - 0 1").unwrap();
+        board.play_san("d5").unwrap();
+        let mut replay: Board = board.clone();
+        assert_eq!(board.play_san("exd6").as_deref(), Some("exd6"));
+        let e5 = Position::from_algebraic("e5").unwrap();
+        let d6 = Position::from_algebraic("d6").unwrap();
+        assert_eq!(replay.move_piece(e5, d6).as_deref(), Ok("exd6"));
+        assert_eq!(board.to_fen(), replay.to_fen());
+    }
+
+    ///Returns the problems found reading the position, or none if it can be played.
+    fn problems(fen: &str) -> Vec<PositionProblem> {
+        match Board::from_fen(fen) {
+            Ok(_) => Vec::new(),
+            Err(FenError::IllegalPosition(problems)) => problems,
+            Err(error) => panic!("{fen}: {error}"),
+        }
+    }
+
+    #[test]
+    fn each_position_problem_is_found() {
+        assert_eq!(problems("4k3/8/8/8/8/8/8/4K3 w - - 0 1"), []);
+        assert_eq!(
+            problems("8/8/8/8/8/8/8/4K3 w - - 0 1"),
+            [PositionProblem::MissingKing(Color::Black)]
+        );
+        assert_eq!(
+            problems("4k3/8/8/8/8/8/8/3KK3 w - - 0 1"),
+            [PositionProblem::ExtraKings(Color::White)]
+        );
+        assert_eq!(
+            problems("P3k3/8/8/8/8/8/8/4K3 w - - 0 1"),
+            [PositionProblem::PawnOnBackRank(
+                Position::from_algebraic("a8").unwrap()
+            )]
+        );
+        assert_eq!(
+            problems("4k3/8/8/8/8/8/8/4R1K1 w - - 0 1"),
+            [PositionProblem::WaitingSideInCheck]
+        );
+        assert_eq!(
+            problems("4k3/8/3N4/1B6/8/8/8/4R1K1 b - - 0 1"),
+            [PositionProblem::TooManyCheckers]
+        );
+        assert_eq!(
+            problems("4k3/3n4/8/3pP3/8/8/8/4K3 w - d6 0 1"),
+            [PositionProblem::InvalidEnPassant]
+        );
+        assert_eq!(
+            problems("4k3/8/8/8/8/P7/PPPPPPPP/4K3 w - - 0 1"),
+            [PositionProblem::TooManyPawns(Color::White)]
+        );
+        assert_eq!(
+            problems("4k3/8/8/8/8/8/PPPPPPP1/QQQ1K3 w - - 0 1"),
+            [PositionProblem::TooManyPieces(Color::White)]
+        );
+        assert_eq!(
+            problems("r3k3/8/8/8/8/8/8/4K3 w Kkq - 0 1"),
+            [
+                PositionProblem::InvalidCastlingRights(Color::White),
+                PositionProblem::InvalidCastlingRights(Color::Black)
+            ]
+        );
+        assert!(matches!(
+            Board::from_fen("4k3/8/8/8/8/8/8/4K3 w X - 0 1"),
+            Err(FenError::InvalidCastlingRights)
+        ));
+    }
+
+    #[test]
+    fn chess960_position_518_is_the_standard_one() {
+        assert_eq!(Board::chess960(518).to_fen(), Board::default().to_fen());
+        assert_ne!(Board::chess960(0).to_fen(), Board::default().to_fen());
+    }
+
+    #[test]
+    fn chess960_castling_rights_name_inner_rooks_by_file() {
+        let fen = "4k1rr/8/8/8/8/8/8/RR2K3 w Bg - 0 1";
+        let board: Board = Board::from_variant_fen(fen, VariantKind::Chess960).unwrap();
+        assert_eq!(board.to_fen(), fen);
+        let outer = "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1";
+        let board: Board = Board::from_variant_fen(outer, VariantKind::Chess960).unwrap();
+        assert_eq!(board.to_fen(), outer);
+        let shredder = "r3k2r/8/8/8/8/8/8/R3K2R w HAha - 0 1";
+        let board: Board = Board::from_variant_fen(shredder, VariantKind::Chess960).unwrap();
+        assert_eq!(board.to_fen(), outer);
+    }
+
+    #[test]
+    fn chess960_castles_by_moving_the_king_onto_the_rook() {
+        let fen = "4k1rr/8/8/8/8/8/8/RR2K3 w Bg - 0 1";
+        let board: Board = Board::from_variant_fen(fen, VariantKind::Chess960).unwrap();
+        let square = |name: &str| Position::from_algebraic(name).unwrap();
+
+        let mut by_king: Board = board.clone();
+        assert_eq!(
+            by_king.move_piece(square("e1"), square("b1")).as_deref(),
+            Ok("O-O-O")
+        );
+        let mut by_san: Board = board.clone();
+        assert_eq!(by_san.play_san("O-O-O").as_deref(), Some("O-O-O"));
+        let mut by_uci: Board = board.clone();
+        assert_eq!(by_uci.move_uci("e1b1").as_deref(), Some("O-O-O"));
+        for board in [&by_king, &by_san, &by_uci] {
+            assert_eq!(board[square("c1")].piece_type, PieceType::King);
+            assert_eq!(board[square("d1")].piece_type, PieceType::Rook);
+            assert_eq!(board[square("a1")].piece_type, PieceType::Rook);
+        }
+        assert_eq!(by_king.last_move_uci().as_deref(), Some("e1b1"));
+
+        assert_eq!(by_san.play_san("O-O").as_deref(), Some("O-O"));
+        assert_eq!(by_san[square("g8")].piece_type, PieceType::King);
+        assert_eq!(by_san[square("f8")].piece_type, PieceType::Rook);
+        assert_eq!(by_san[square("h8")].piece_type, PieceType::Rook);
+        assert_eq!(by_san.to_fen(), "5rkr/8/8/8/8/8/8/R1KR4 w - - 2 2");
+    }
+
+    #[test]
+    fn en_passant_needs_a_double_step() {
+        let mut board = Board::from_fen("4k3/8/3p4/4P3/8/8/8/4K3 b - - 0 1").unwrap();
+        board.play_san("d5").unwrap();
+        assert!(board.to_fen().contains(" w - "));
+        assert!(board.clone().play_san("exd6").is_none());
+
+        let mut board = Board::from_fen("4k3/3p4/8/4P3/8/8/8/4K3 b - - 0 1").unwrap();
+        board.play_san("d5").unwrap();
+        assert!(board.to_fen().contains(" w - d6 "));
+        assert!(board.clone().play_san("exd6").is_some());
+    }
+
+    #[test]
+    fn only_pawns_capture_en_passant() {
+        let mut board: Board =
+            Board::from_variant_fen("4k3/3p4/8/8/2N5/8/8/4K3 b - - 0 1", VariantKind::Antichess)
+                .unwrap();
+        board.play_san("d5").unwrap();
+        assert!(board.capturing_pieces().is_empty());
+        assert!(board.clone().play_san("Kd2").is_some());
+        assert!(board.clone().play_san("Nd6").is_some());
+    }
+
+    #[test]
+    fn uci_promotion_reads_and_writes_piece_letter() {
+        let mut board = Board::from_fen("4k3/P7/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        assert!(board.clone().move_uci("a7a8k").is_none());
+        assert_eq!(board.move_uci("a7a8n").as_deref(), Some("a8=N"));
+        assert_eq!(board.last_move_uci().as_deref(), Some("a7a8n"));
+        assert_eq!(board.move_uci("e8d8").as_deref(), Some("Kd8"));
+        assert_eq!(board.last_move_uci().as_deref(), Some("e8d8"));
+    }
+
+    #[test]
+    fn captured_promoted_piece_goes_to_pocket_as_pawn() {
+        let mut board =
+            Board::from_variant_fen("8/1Pk5/8/8/8/8/8/4K3[] w - - 0 1", VariantKind::Crazyhouse)
+                .unwrap();
+        assert_eq!(board.play_san("b8=Q+").as_deref(), Some("b8=Q+"));
+        assert!(board.to_fen().starts_with("1Q~6/2k5/"));
+        assert_eq!(board.play_san("Kxb8").as_deref(), Some("Kxb8"));
+        assert_eq!(board.pocket_count(Color::Black, PieceType::Pawn), 1);
+        assert_eq!(board.pocket_count(Color::Black, PieceType::Queen), 0);
+    }
+
+    ///Plays random legal moves and drops in every variant, checking that each position reached
+    ///can be read back from its FEN.
+    #[test]
+    fn positions_from_play_pass_validation() {
+        let mut seed: u64 = 0x2545_F491_4F6C_DD1D;
+        let mut random = move |bound: usize| {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            (seed % bound as u64) as usize
+        };
+        for variant in VariantKind::ALL {
+            for game in 0..6 {
+                let mut board: Board = match variant {
+                    VariantKind::Chess960 => Board::chess960(game * 151),
+                    _ => variant.rules().starting_board(),
+                };
+                board.variant = variant;
+                for _ in 0..120 {
+                    if board.outcome().is_some() {
+                        break;
+                    }
+                    let moves: Vec<(Position, Position)> = board.legal_moves();
+                    let drops: Vec<(PieceType, Position)> = board.legal_drops();
+                    let pick: usize = random(moves.len() + drops.len());
+                    if let Some((old_position, new_position)) = moves.get(pick).copied() {
+                        let promotions: Vec<PieceType> = board.promotion_pieces();
+                        let promotion: PieceType = promotions[random(promotions.len())];
+                        let promotion: PieceType = if board.is_promotion(old_position, new_position)
+                        {
+                            promotion
+                        } else {
+                            PieceType::Queen
+                        };
+                        board
+                            .promote_piece(old_position, new_position, promotion)
+                            .unwrap();
+                    } else {
+                        let (piece_type, position) = drops[pick - moves.len()];
+                        board.drop_piece(piece_type, position).unwrap();
+                    }
+                    let fen: String = board.to_fen();
+                    assert!(
+                        Board::from_variant_fen(&fen, variant).is_ok(),
+                        "{variant}: {fen}"
+                    );
+                }
+            }
+        }
+    }
+}
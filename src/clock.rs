@@ -0,0 +1,426 @@
+use crate::piece::Color;
+use std::{
+    fmt,
+    time::{Duration, Instant},
+};
+
+///How extra time is given for each move.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Timing {
+    ///No extra time.
+    SuddenDeath,
+    ///Adds a fixed amount of time after every move (Fischer).
+    Increment(Duration),
+    ///Gives back the time used on a move, up to a fixed amount (Bronstein).
+    Bronstein(Duration),
+    ///Waits a fixed amount of time before the clock starts running on each move.
+    Delay(Duration),
+}
+
+///One stage of a time control, such as 40 moves in 90 minutes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Stage {
+    ///The number of moves to play in this stage, or `None` for the rest of the game.
+    pub moves: Option<u32>,
+    pub time: Duration,
+    pub timing: Timing,
+}
+
+impl Stage {
+    ///Returns a stage lasting for the rest of the game.
+    pub fn new(minutes: u64, timing: Timing) -> Self {
+        Self {
+            moves: None,
+            time: Duration::from_secs(minutes * 60),
+            timing,
+        }
+    }
+    ///Returns a stage lasting for the given number of moves.
+    pub fn with_moves(moves: u32, minutes: u64, timing: Timing) -> Self {
+        Self {
+            moves: Some(moves),
+            ..Self::new(minutes, timing)
+        }
+    }
+}
+
+///The stages a game is played under, or no clock at all if there are none.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TimeControl {
+    pub stages: Vec<Stage>,
+}
+
+impl TimeControl {
+    ///Returns the time controls offered in the new game dialog.
+    pub fn presets() -> Vec<Self> {
+        let seconds = Duration::from_secs;
+        vec![
+            Self::default(),
+            Self {
+                stages: vec![Stage::new(1, Timing::SuddenDeath)],
+            },
+            Self {
+                stages: vec![Stage::new(3, Timing::Increment(seconds(2)))],
+            },
+            Self {
+                stages: vec![Stage::new(5, Timing::Bronstein(seconds(3)))],
+            },
+            Self {
+                stages: vec![Stage::new(15, Timing::Delay(seconds(5)))],
+            },
+            Self {
+                stages: vec![
+                    Stage::with_moves(40, 90, Timing::Increment(seconds(30))),
+                    Stage::new(30, Timing::Increment(seconds(30))),
+                ],
+            },
+        ]
+    }
+    ///Returns the time control as written in the PGN `TimeControl` tag, such as `40/5400+30:1800+30`.
+    ///
+    ///PGN has no way to write delays, so only Fischer increments are kept.
+    pub fn pgn_tag(&self) -> String {
+        if self.is_untimed() {
+            return "-".to_string();
+        }
+        let stages: Vec<String> = self
+            .stages
+            .iter()
+            .map(|stage| {
+                let mut tag = match stage.moves {
+                    Some(moves) => format!("{moves}/{}", stage.time.as_secs()),
+                    None => stage.time.as_secs().to_string(),
+                };
+                if let Timing::Increment(increment) = stage.timing {
+                    tag.push_str(&format!("+{}", increment.as_secs()));
+                }
+                tag
+            })
+            .collect();
+        stages.join(":")
+    }
+    ///Checks if the game is played without a clock.
+    pub fn is_untimed(&self) -> bool {
+        self.stages.is_empty()
+    }
+}
+
+impl fmt::Display for TimeControl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_untimed() {
+            return write!(f, "No Clock");
+        }
+        for (i, stage) in self.stages.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", then ")?;
+            }
+            if let Some(moves) = stage.moves {
+                write!(f, "{moves}/")?;
+            }
+            write!(f, "{}", stage.time.as_secs() / 60)?;
+            match stage.timing {
+                Timing::SuddenDeath => write!(f, " min")?,
+                Timing::Increment(increment) => write!(f, "+{}", increment.as_secs())?,
+                Timing::Bronstein(delay) => write!(f, " min, {}s Bronstein", delay.as_secs())?,
+                Timing::Delay(delay) => write!(f, " min, {}s delay", delay.as_secs())?,
+            }
+        }
+        Ok(())
+    }
+}
+
+///What pressing the clock after a move changed, so the move can be taken back.
+#[derive(Debug, Clone, Copy)]
+struct Press {
+    color: Color,
+    ///The side's stage and moves in it before the press.
+    stage: usize,
+    moves_in_stage: u32,
+    ///The time the move earned, from its increment or a new stage.
+    earned: Duration,
+}
+
+///A chess clock counting down both sides' time.
+#[derive(Debug, Clone)]
+pub struct Clock {
+    time_control: TimeControl,
+    remaining: [Duration; 2],
+    stage: [usize; 2],
+    moves_in_stage: [u32; 2],
+    running: Option<(Color, Instant)>,
+    ///Every press so far, oldest first.
+    presses: Vec<Press>,
+}
+
+impl Clock {
+    ///Creates a clock with both sides' time set for the first stage.
+    pub fn new(time_control: TimeControl) -> Self {
+        let time: Duration = time_control
+            .stages
+            .first()
+            .map_or(Duration::ZERO, |stage| stage.time);
+        Self {
+            time_control,
+            remaining: [time; 2],
+            stage: [0; 2],
+            moves_in_stage: [0; 2],
+            running: None,
+            presses: Vec::new(),
+        }
+    }
+    ///Returns the time left for a side, counting the move in progress.
+    pub fn remaining(&self, color: Color, now: Instant) -> Duration {
+        let remaining: Duration = self.remaining[Self::index(color)];
+        match self.running {
+            Some((running_color, started)) if running_color == color => {
+                let mut elapsed: Duration = now.saturating_duration_since(started);
+                if let Timing::Delay(delay) = self.timing(color) {
+                    elapsed = elapsed.saturating_sub(delay);
+                }
+                remaining.saturating_sub(elapsed)
+            }
+            _ => remaining,
+        }
+    }
+    ///Returns the side whose clock is running.
+    pub fn running_side(&self) -> Option<Color> {
+        self.running.map(|(color, _)| color)
+    }
+    ///Returns the side that has run out of time, if any.
+    pub fn flagged_side(&self, now: Instant) -> Option<Color> {
+        self.running_side()
+            .filter(|color| self.remaining(*color, now).is_zero())
+    }
+    ///Stops the clock of the side that just moved and starts the opponent's.
+    ///
+    ///The first move of the game starts the opponent's clock without using any time. A clock
+    ///without stages never runs.
+    pub fn press(&mut self, color: Color, now: Instant) {
+        if self.time_control.is_untimed() {
+            return;
+        }
+        let index = Self::index(color);
+        let mut press = Press {
+            color,
+            stage: self.stage[index],
+            moves_in_stage: self.moves_in_stage[index],
+            earned: Duration::ZERO,
+        };
+        match self.running {
+            Some((running_color, _)) if running_color != color => return,
+            Some((_, started)) => {
+                let elapsed: Duration = now.saturating_duration_since(started);
+                self.remaining[index] = self.remaining(color, now);
+                press.earned = match self.timing(color) {
+                    Timing::Increment(increment) => increment,
+                    Timing::Bronstein(delay) => elapsed.min(delay),
+                    Timing::SuddenDeath | Timing::Delay(_) => Duration::ZERO,
+                };
+            }
+            None => (),
+        }
+        self.moves_in_stage[index] += 1;
+        let stage: Stage = self.time_control.stages[self.stage[index]];
+        if stage.moves == Some(self.moves_in_stage[index])
+            && self.stage[index] + 1 < self.time_control.stages.len()
+        {
+            self.stage[index] += 1;
+            self.moves_in_stage[index] = 0;
+            press.earned += self.time_control.stages[self.stage[index]].time;
+        }
+        self.remaining[index] += press.earned;
+        self.presses.push(press);
+        self.running = Some((!color, now));
+    }
+    ///Stops both clocks, keeping the time left.
+    pub fn stop(&mut self, now: Instant) {
+        if let Some((color, _)) = self.running {
+            self.remaining[Self::index(color)] = self.remaining(color, now);
+            self.running = None;
+        }
+    }
+    ///Sets a side's time left to match another clock, such as the opponent's in a game over the
+    ///network, restarting the side's move from now if its clock is running.
+    pub fn sync(&mut self, color: Color, remaining: Duration, now: Instant) {
+        self.remaining[Self::index(color)] = remaining;
+        if self.running_side() == Some(color) {
+            self.running = Some((color, now));
+        }
+    }
+    ///Takes back the given number of moves and starts the given side's clock. The time spent on
+    ///the moves stays used, but the time they earned and their count towards the stage are
+    ///given back.
+    pub fn take_back(&mut self, moves: usize, color: Color, now: Instant) {
+        self.stop(now);
+        for _ in 0..moves {
+            let Some(press) = self.presses.pop() else {
+                break;
+            };
+            let index = Self::index(press.color);
+            self.stage[index] = press.stage;
+            self.moves_in_stage[index] = press.moves_in_stage;
+            self.remaining[index] = self.remaining[index].saturating_sub(press.earned);
+        }
+        if !self.time_control.is_untimed() {
+            self.running = Some((color, now));
+        }
+    }
+    ///Returns how extra time is given to a side in its current stage.
+    fn timing(&self, color: Color) -> Timing {
+        self.time_control.stages[self.stage[Self::index(color)]].timing
+    }
+    fn index(color: Color) -> usize {
+        match color {
+            Color::White => 0,
+            Color::Black => 1,
+        }
+    }
+}
+
+///Formats a clock time as hours, minutes and seconds, showing tenths in the last ten seconds.
+pub fn format_time(time: Duration) -> String {
+    let seconds = time.as_secs();
+    if seconds >= 3600 {
+        format!(
+            "{}:{:02}:{:02}",
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60
+        )
+    } else if seconds >= 10 {
+        format!("{}:{:02}", seconds / 60, seconds % 60)
+    } else {
+        format!("0:{:02}.{}", seconds, time.subsec_millis() / 100)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seconds(seconds: u64) -> Duration {
+        Duration::from_secs(seconds)
+    }
+
+    #[test]
+    fn untimed_clock_never_runs() {
+        let mut clock = Clock::new(TimeControl::default());
+        let start = Instant::now();
+        clock.press(Color::White, start);
+        clock.take_back(1, Color::White, start);
+        assert_eq!(clock.running_side(), None);
+        assert_eq!(clock.flagged_side(start + seconds(60)), None);
+    }
+
+    #[test]
+    fn first_press_starts_opponents_clock() {
+        let mut clock = Clock::new(TimeControl {
+            stages: vec![Stage::new(1, Timing::SuddenDeath)],
+        });
+        let start = Instant::now();
+        clock.press(Color::White, start + seconds(10));
+        assert_eq!(clock.running_side(), Some(Color::Black));
+        assert_eq!(
+            clock.remaining(Color::White, start + seconds(30)),
+            seconds(60)
+        );
+        assert_eq!(
+            clock.remaining(Color::Black, start + seconds(30)),
+            seconds(40)
+        );
+        assert_eq!(clock.flagged_side(start + seconds(70)), Some(Color::Black));
+        // Pressing out of turn does nothing.
+        clock.press(Color::White, start + seconds(30));
+        assert_eq!(clock.running_side(), Some(Color::Black));
+    }
+
+    #[test]
+    fn increment_is_added_after_each_move() {
+        let mut clock = Clock::new(TimeControl {
+            stages: vec![Stage::new(1, Timing::Increment(seconds(2)))],
+        });
+        let start = Instant::now();
+        clock.press(Color::White, start);
+        clock.press(Color::Black, start + seconds(10));
+        assert_eq!(clock.remaining(Color::Black, start), seconds(52));
+    }
+
+    #[test]
+    fn bronstein_gives_back_time_used_up_to_the_delay() {
+        let mut clock = Clock::new(TimeControl {
+            stages: vec![Stage::new(1, Timing::Bronstein(seconds(3)))],
+        });
+        let start = Instant::now();
+        clock.press(Color::White, start);
+        clock.press(Color::Black, start + seconds(2));
+        assert_eq!(clock.remaining(Color::Black, start), seconds(60));
+        clock.press(Color::White, start + seconds(12));
+        assert_eq!(clock.remaining(Color::White, start), seconds(53));
+    }
+
+    #[test]
+    fn delay_waits_before_the_clock_runs() {
+        let mut clock = Clock::new(TimeControl {
+            stages: vec![Stage::new(1, Timing::Delay(seconds(5)))],
+        });
+        let start = Instant::now();
+        clock.press(Color::White, start);
+        assert_eq!(
+            clock.remaining(Color::Black, start + seconds(4)),
+            seconds(60)
+        );
+        assert_eq!(
+            clock.remaining(Color::Black, start + seconds(15)),
+            seconds(50)
+        );
+        clock.press(Color::Black, start + seconds(15));
+        assert_eq!(clock.remaining(Color::Black, start), seconds(50));
+    }
+
+    #[test]
+    fn next_stage_adds_its_time_after_the_moves() {
+        let mut clock = Clock::new(TimeControl {
+            stages: vec![
+                Stage::with_moves(2, 1, Timing::SuddenDeath),
+                Stage::new(1, Timing::Increment(seconds(1))),
+            ],
+        });
+        let start = Instant::now();
+        clock.press(Color::White, start);
+        clock.press(Color::Black, start + seconds(10));
+        assert_eq!(clock.remaining(Color::Black, start), seconds(50));
+        clock.press(Color::White, start + seconds(20));
+        assert_eq!(clock.remaining(Color::White, start), seconds(110));
+        clock.press(Color::Black, start + seconds(30));
+        assert_eq!(clock.remaining(Color::Black, start), seconds(100));
+        // The second stage's increment applies from then on.
+        clock.press(Color::White, start + seconds(40));
+        assert_eq!(clock.remaining(Color::White, start), seconds(101));
+    }
+
+    #[test]
+    fn take_back_returns_earned_time_and_stage() {
+        let mut clock = Clock::new(TimeControl {
+            stages: vec![
+                Stage::with_moves(1, 1, Timing::Increment(seconds(2))),
+                Stage::new(1, Timing::Increment(seconds(2))),
+            ],
+        });
+        let start = Instant::now();
+        clock.press(Color::White, start);
+        clock.press(Color::Black, start + seconds(5));
+        assert_eq!(
+            clock.remaining(Color::Black, start),
+            seconds(115) + seconds(2)
+        );
+        clock.take_back(1, Color::Black, start + seconds(5));
+        assert_eq!(
+            clock.remaining(Color::Black, start + seconds(5)),
+            seconds(55)
+        );
+        assert_eq!(clock.running_side(), Some(Color::Black));
+        // The move counts towards the first stage again.
+        clock.press(Color::Black, start + seconds(5));
+        assert_eq!(clock.remaining(Color::Black, start), seconds(117));
+    }
+}
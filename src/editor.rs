@@ -0,0 +1,108 @@
+use crate::{
+    board::{Board, FenError},
+    piece::{Color, Piece, PieceType, Position},
+};
+
+///The castling rights in the order they are written in FEN.
+pub const CASTLING_LETTERS: [char; 4] = ['K', 'Q', 'k', 'q'];
+
+///A position being set up by hand, piece by piece.
+#[derive(Clone)]
+pub struct PositionEditor {
+    ///Holds the placed pieces. Everything else about it is ignored.
+    pub placement: Board,
+    pub turn: Color,
+    ///Which of the castling rights in `CASTLING_LETTERS` are on.
+    pub castling: [bool; 4],
+    ///The square a pawn can be captured on en passant, such as `e3`, or blank for none.
+    pub en_passant: String,
+    ///The piece placed by clicking a square, or `None` to remove pieces.
+    pub brush: Option<(Color, PieceType)>,
+}
+
+impl PositionEditor {
+    ///Starts editing from the position on the given board.
+    pub fn new(board: &Board) -> Self {
+        let fen: String = board.to_fen();
+        let fields: Vec<&str> = fen.split_whitespace().collect();
+        Self {
+            // Reading the board back from FEN drops its last move, which would be highlighted.
+            placement: Board::from_variant_fen(&fen, board.variant)
+                .unwrap_or_else(|_| board.clone()),
+            turn: board.turn,
+            castling: CASTLING_LETTERS.map(|letter| fields[2].contains(letter)),
+            en_passant: fields[3].replace('-', ""),
+            brush: Some((Color::White, PieceType::Pawn)),
+        }
+    }
+    ///Puts the chosen piece on a square, or empties it if the piece is already there.
+    pub fn put(&mut self, position: Position) {
+        let piece = self.placement[position];
+        self.placement[position] = match self.brush {
+            Some((color, piece_type)) if piece.piece_type != piece_type || piece.color != color => {
+                Piece::new(piece_type, position, color)
+            }
+            _ => Piece::new(PieceType::None, position, Color::White),
+        };
+    }
+    pub fn remove(&mut self, position: Position) {
+        self.placement[position] = Piece::new(PieceType::None, position, Color::White);
+    }
+    ///Moves a piece to another square, replacing whatever was there.
+    pub fn move_piece(&mut self, old_position: Position, new_position: Position) {
+        let piece = self.placement[old_position];
+        self.remove(old_position);
+        self.placement[new_position] = Piece::new(piece.piece_type, new_position, piece.color);
+    }
+    ///Removes every piece and castling right.
+    pub fn clear(&mut self) {
+        for y in 0..8 {
+            for x in 0..8 {
+                self.remove(Position::new(x, y));
+            }
+        }
+        self.castling = [false; 4];
+        self.en_passant.clear();
+    }
+    ///Sets the pieces up for a normal game.
+    pub fn reset(&mut self) {
+        let mut board = Board::default();
+        board.variant = self.placement.variant;
+        *self = Self {
+            brush: self.brush,
+            ..Self::new(&board)
+        };
+    }
+    ///Returns the position in Forsyth-Edwards Notation.
+    pub fn fen(&self) -> String {
+        let placement_fen: String = self.placement.to_fen();
+        let placement: &str = placement_fen.split(' ').next().unwrap_or_default();
+        let mut castling: String = CASTLING_LETTERS
+            .iter()
+            .zip(self.castling)
+            .filter(|(_, is_allowed)| *is_allowed)
+            .map(|(letter, _)| *letter)
+            .collect();
+        if castling.is_empty() {
+            castling.push('-');
+        }
+        let en_passant: &str = match self.en_passant.trim() {
+            "" => "-",
+            square => square,
+        };
+        format!(
+            "{placement} {} {castling} {en_passant} 0 1",
+            if self.turn == Color::White { "w" } else { "b" },
+        )
+    }
+    ///Returns the board to play from, or the reasons the position can't be played by the rules
+    ///of the variant being played.
+    pub fn board(&self) -> Result<Board, Vec<String>> {
+        Board::from_variant_fen(&self.fen(), self.placement.variant).map_err(|error| match error {
+            FenError::IllegalPosition(problems) => {
+                problems.iter().map(ToString::to_string).collect()
+            }
+            error => vec![error.to_string()],
+        })
+    }
+}
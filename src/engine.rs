@@ -0,0 +1,448 @@
+use crate::{
+    board::{Board, Outcome, POCKET_PIECES},
+    piece::{Color, PieceType, Position},
+};
+use std::{
+    fmt,
+    io::{self, BufRead, BufReader, Write},
+    process::{Child, ChildStdin, ChildStdout, Command, Stdio},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+};
+
+const MATE_SCORE: i32 = 100_000;
+///The deepest the built-in engine searches, which is also its highest level.
+pub const MAX_DEPTH: u32 = 4;
+
+///The evaluation of a position from White's point of view.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Score {
+    Centipawns(i32),
+    ///Mate in the given number of moves, negative if Black is mating.
+    Mate(i32),
+}
+
+impl Score {
+    ///Returns White's share of the evaluation bar, from 0 to 1.
+    pub fn white_share(self) -> f32 {
+        match self {
+            Self::Centipawns(centipawns) => 1.0 / (1.0 + 10f32.powf(-centipawns as f32 / 400.0)),
+            Self::Mate(moves) if moves > 0 => 1.0,
+            Self::Mate(_) => 0.0,
+        }
+    }
+    ///Turns a score for the side to move into a score from White's point of view.
+    fn for_white(self, turn: Color) -> Self {
+        match (self, turn) {
+            (_, Color::White) => self,
+            (Self::Centipawns(centipawns), Color::Black) => Self::Centipawns(-centipawns),
+            (Self::Mate(moves), Color::Black) => Self::Mate(-moves),
+        }
+    }
+    ///Turns a search score for the side to move into a score from White's point of view.
+    fn from_search(score: i32, turn: Color) -> Self {
+        let score = if score.abs() > MATE_SCORE - 1000 {
+            let moves = (MATE_SCORE - score.abs() + 1) / 2;
+            Self::Mate(if score > 0 { moves } else { -moves })
+        } else {
+            Self::Centipawns(score)
+        };
+        score.for_white(turn)
+    }
+}
+
+impl fmt::Display for Score {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Centipawns(centipawns) => write!(f, "{:+.2}", *centipawns as f32 / 100.0),
+            Self::Mate(moves) if *moves < 0 => write!(f, "-#{}", -moves),
+            Self::Mate(moves) => write!(f, "#{moves}"),
+        }
+    }
+}
+
+///A principal variation, with its moves in SAN.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Line {
+    pub score: Score,
+    pub moves: Vec<String>,
+}
+
+///The latest result of analysing a position.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Analysis {
+    pub depth: u32,
+    pub lines: Vec<Line>,
+    ///Whether the engine has stopped searching.
+    pub finished: bool,
+}
+
+///The engine used for analysis.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum EngineKind {
+    #[default]
+    BuiltIn,
+    ///An external engine speaking UCI, started from the given path.
+    Uci(String),
+}
+
+///Analyses a position on a background thread until it is dropped.
+pub struct Analyzer {
+    analysis: Arc<Mutex<Analysis>>,
+    stop: Arc<AtomicBool>,
+    process: Option<Child>,
+}
+
+impl Analyzer {
+    ///Starts analysing the given position, keeping the best `line_count` lines.
+    ///
+    ///The search stops at `max_depth` if one is given, and runs until dropped otherwise.
+    pub fn start(
+        engine: &EngineKind,
+        board: &Board,
+        line_count: usize,
+        max_depth: Option<u32>,
+    ) -> io::Result<Self> {
+        let analysis: Arc<Mutex<Analysis>> = Arc::new(Mutex::new(Analysis::default()));
+        let stop: Arc<AtomicBool> = Arc::new(AtomicBool::new(false));
+        let board: Board = board.clone();
+        let mut process: Option<Child> = None;
+
+        match engine {
+            EngineKind::BuiltIn => {
+                let analysis = Arc::clone(&analysis);
+                let stop = Arc::clone(&stop);
+                let max_depth: u32 = max_depth.unwrap_or(MAX_DEPTH).clamp(1, MAX_DEPTH);
+                thread::spawn(move || {
+                    for depth in 1..=max_depth {
+                        let Some(lines) = search_lines(&board, depth, line_count, &stop) else {
+                            return;
+                        };
+                        *analysis.lock().unwrap() = Analysis {
+                            depth,
+                            lines,
+                            finished: depth == max_depth,
+                        };
+                    }
+                });
+            }
+            EngineKind::Uci(path) => {
+                let mut child: Child = Command::new(path)
+                    .stdin(Stdio::piped())
+                    .stdout(Stdio::piped())
+                    .stderr(Stdio::null())
+                    .spawn()?;
+                let stdin: ChildStdin = child.stdin.take().unwrap();
+                let stdout: ChildStdout = child.stdout.take().unwrap();
+                let analysis = Arc::clone(&analysis);
+                let stop = Arc::clone(&stop);
+                thread::spawn(move || {
+                    let _ = run_uci(
+                        stdin, stdout, &board, line_count, max_depth, &analysis, &stop,
+                    );
+                });
+                process = Some(child);
+            }
+        }
+        Ok(Self {
+            analysis,
+            stop,
+            process,
+        })
+    }
+    ///Returns the latest analysis.
+    pub fn analysis(&self) -> Analysis {
+        self.analysis.lock().unwrap().clone()
+    }
+}
+
+impl Drop for Analyzer {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(process) = &mut self.process {
+            let _ = process.kill();
+            let _ = process.wait();
+        }
+    }
+}
+
+///Checks if an engine playing the given side agrees to a draw, which it does when the
+///position looks worse for it by the built-in evaluation.
+pub fn accepts_draw(board: &Board, color: Color) -> bool {
+    let score: i32 = evaluate(board);
+    let score: i32 = if board.turn == color { score } else { -score };
+    score < 0
+}
+
+///Talks to an external UCI engine, updating the analysis with every `info` line it sends.
+fn run_uci(
+    mut stdin: ChildStdin,
+    stdout: ChildStdout,
+    board: &Board,
+    line_count: usize,
+    max_depth: Option<u32>,
+    analysis: &Mutex<Analysis>,
+    stop: &AtomicBool,
+) -> io::Result<()> {
+    let mut output = BufReader::new(stdout).lines();
+    writeln!(stdin, "uci")?;
+    for line in output.by_ref() {
+        if line?.trim() == "uciok" {
+            break;
+        }
+    }
+    writeln!(stdin, "setoption name MultiPV value {line_count}")?;
+    // Castling is then sent and received as the king moving onto its rook.
+    if board.variant.rules().castles_onto_rook() {
+        writeln!(stdin, "setoption name UCI_Chess960 value true")?;
+    }
+    writeln!(stdin, "position fen {}", board.to_fen())?;
+    match max_depth {
+        Some(depth) => writeln!(stdin, "go depth {depth}")?,
+        None => writeln!(stdin, "go infinite")?,
+    }
+
+    let mut lines: Vec<Option<Line>> = vec![None; line_count];
+    for line in output {
+        if stop.load(Ordering::Relaxed) {
+            break;
+        }
+        let line: String = line?;
+        if let Some(best_move) = line.strip_prefix("bestmove") {
+            let mut analysis = analysis.lock().unwrap();
+            // Engines may skip `info pv` lines on quick searches, so the best move is used alone.
+            if analysis.lines.is_empty() {
+                let uci: &str = best_move.split_whitespace().next().unwrap_or_default();
+                if let Some(notation) = board.clone().move_uci(uci) {
+                    analysis.lines = vec![Line {
+                        score: Score::from_search(evaluate(board), board.turn),
+                        moves: vec![notation],
+                    }];
+                }
+            }
+            analysis.finished = true;
+            break;
+        }
+        let Some((depth, index, pv)) = parse_info(&line, board) else {
+            continue;
+        };
+        if index >= line_count {
+            continue;
+        }
+        lines[index] = Some(pv);
+        let mut analysis = analysis.lock().unwrap();
+        if index == 0 {
+            analysis.depth = depth;
+        }
+        analysis.lines = lines.iter().flatten().cloned().collect();
+    }
+    let _ = writeln!(stdin, "quit");
+    Ok(())
+}
+
+///Reads the depth, line index and principal variation from a UCI `info` line.
+fn parse_info(info: &str, board: &Board) -> Option<(u32, usize, Line)> {
+    let mut tokens = info.split_whitespace();
+    if tokens.next()? != "info" {
+        return None;
+    }
+    let mut depth: u32 = 0;
+    let mut index: usize = 0;
+    let mut score: Option<Score> = None;
+    let mut moves: Vec<String> = Vec::new();
+    while let Some(token) = tokens.next() {
+        match token {
+            "depth" => depth = tokens.next()?.parse().ok()?,
+            "multipv" => index = tokens.next()?.parse::<usize>().ok()?.checked_sub(1)?,
+            "score" => {
+                let kind = tokens.next()?;
+                let value: i32 = tokens.next()?.parse().ok()?;
+                score = match kind {
+                    "cp" => Some(Score::Centipawns(value)),
+                    "mate" => Some(Score::Mate(value)),
+                    _ => None,
+                };
+            }
+            "pv" => {
+                let mut board: Board = board.clone();
+                for uci in tokens.by_ref() {
+                    match board.move_uci(uci) {
+                        Some(notation) => moves.push(notation),
+                        None => break,
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+    if moves.is_empty() {
+        return None;
+    }
+    Some((
+        depth,
+        index,
+        Line {
+            score: score?.for_white(board.turn),
+            moves,
+        },
+    ))
+}
+
+///Searches every legal move to the given depth, returning the best lines.
+fn search_lines(
+    board: &Board,
+    depth: u32,
+    line_count: usize,
+    stop: &AtomicBool,
+) -> Option<Vec<Line>> {
+    let mut lines: Vec<(i32, Vec<String>)> = Vec::new();
+    for (notation, child) in children(board) {
+        let (score, mut moves) = if notation.ends_with('#') {
+            (MATE_SCORE - 1, Vec::new())
+        } else {
+            let (score, moves) = negamax(&child, depth - 1, -MATE_SCORE, MATE_SCORE, 1, stop)?;
+            (-score, moves)
+        };
+        moves.insert(0, notation);
+        lines.push((score, moves));
+    }
+    lines.sort_by_key(|(score, _)| -score);
+    Some(
+        lines
+            .into_iter()
+            .take(line_count)
+            .map(|(score, moves)| Line {
+                score: Score::from_search(score, board.turn),
+                moves,
+            })
+            .collect(),
+    )
+}
+
+///Returns the score of the position for the side to move and the best line found.
+fn negamax(
+    board: &Board,
+    depth: u32,
+    mut alpha: i32,
+    beta: i32,
+    ply: i32,
+    stop: &AtomicBool,
+) -> Option<(i32, Vec<String>)> {
+    if stop.load(Ordering::Relaxed) {
+        return None;
+    }
+    if depth == 0 {
+        return Some((evaluate(board), Vec::new()));
+    }
+    let rules = board.variant.rules();
+    if let Some(outcome) = rules.outcome(board) {
+        return Some((outcome_score(outcome, board.turn, ply), Vec::new()));
+    }
+    let children: Vec<(String, Board)> = children(board);
+    if children.is_empty() {
+        let outcome: Outcome = rules.outcome_without_moves(board);
+        return Some((outcome_score(outcome, board.turn, ply), Vec::new()));
+    }
+    let mut best: (i32, Vec<String>) = (-MATE_SCORE, Vec::new());
+    for (notation, child) in children {
+        let (score, mut line) = if notation.ends_with('#') {
+            (MATE_SCORE - ply - 1, Vec::new())
+        } else {
+            let (score, line) = negamax(&child, depth - 1, -beta, -alpha, ply + 1, stop)?;
+            (-score, line)
+        };
+        if score > best.0 || best.1.is_empty() {
+            line.insert(0, notation);
+            best = (score, line);
+        }
+        alpha = alpha.max(score);
+        if alpha >= beta {
+            break;
+        }
+    }
+    Some(best)
+}
+
+///Plays every legal move and drop, returning each one's SAN with the board after it.
+fn children(board: &Board) -> Vec<(String, Board)> {
+    let mut children: Vec<(String, Board)> = Vec::new();
+    for (old_position, new_position) in board.legal_moves() {
+        let promotions: Vec<PieceType> = if board.is_promotion(old_position, new_position) {
+            board.promotion_pieces()
+        } else {
+            vec![PieceType::Queen]
+        };
+        for promotion in promotions {
+            let mut child: Board = board.clone();
+            if let Ok(notation) = child.promote_piece(old_position, new_position, promotion) {
+                children.push((notation, child));
+            }
+        }
+    }
+    for (piece_type, position) in board.legal_drops() {
+        let mut child: Board = board.clone();
+        if let Ok(notation) = child.drop_piece(piece_type, position) {
+            children.push((notation, child));
+        }
+    }
+    children
+}
+
+///Returns the score of a finished game for the side to move, preferring quicker wins.
+fn outcome_score(outcome: Outcome, turn: Color, ply: i32) -> i32 {
+    match outcome.winner() {
+        Some(winner) if winner == turn => MATE_SCORE - ply,
+        Some(_) => -(MATE_SCORE - ply),
+        None => 0,
+    }
+}
+///Returns a static evaluation of the position for the side to move.
+fn evaluate(board: &Board) -> i32 {
+    let mut score: i32 = 0;
+    for y in 0u8..8u8 {
+        for x in 0u8..8u8 {
+            let piece = board[Position::new(x, y)];
+            let advancement = i32::from(if piece.color == Color::White {
+                7 - y
+            } else {
+                y
+            });
+            let centralization = 14 - (2 * i32::from(x) - 7).abs() - (2 * i32::from(y) - 7).abs();
+            let value = match piece.piece_type {
+                PieceType::None => 0,
+                PieceType::Pawn => 100 + (advancement - 1) * 5,
+                PieceType::Knight => 320 + centralization * 2,
+                PieceType::Bishop => 330 + centralization,
+                PieceType::Rook => 500,
+                PieceType::Queen => 900 + centralization,
+                PieceType::King => 0,
+            };
+            if piece.color == Color::White {
+                score += value;
+            } else {
+                score -= value;
+            }
+        }
+    }
+    // Pieces in hand count at their plain value, as they can be dropped anywhere.
+    for piece_type in POCKET_PIECES {
+        let value: i32 = match piece_type {
+            PieceType::Pawn => 100,
+            PieceType::Knight => 320,
+            PieceType::Bishop => 330,
+            PieceType::Rook => 500,
+            _ => 900,
+        };
+        score += value
+            * (i32::from(board.pocket_count(Color::White, piece_type))
+                - i32::from(board.pocket_count(Color::Black, piece_type)));
+    }
+    if board.turn == Color::White {
+        score
+    } else {
+        -score
+    }
+}
@@ -1,13 +1,23 @@
 use iced::Point;
+use std::time::Instant;
 use ui::UI;
 
 mod board;
+mod clock;
+mod editor;
+mod engine;
+mod net;
+mod overlay;
+mod pgn;
 mod piece;
+mod setup;
+mod tree;
 mod ui;
+mod variant;
 fn main() -> iced::Result {
     iced::application("Chess", UI::update, UI::view)
         .window(UI::window())
-        .subscription(UI::trigger_window_event)
+        .subscription(UI::subscription)
         .run()
 }
 
@@ -18,6 +28,51 @@ pub enum Message {
     CursorMoved(Point),
     LeftButtonPressed,
     LeftButtonReleased,
-    RestartButtonPressed,
+    RightButtonPressed,
+    RightButtonReleased,
+    PocketPiecePressed(piece::PieceType),
+    PromotionSelected(Option<piece::PieceType>),
+    NewGameButtonPressed,
+    SetupChanged(setup::GameSetup),
+    CancelSetupButtonPressed,
+    StartGameButtonPressed,
+    ResignButtonPressed,
+    OfferDrawButtonPressed,
+    RequestTakebackButtonPressed,
+    AcceptOfferButtonPressed,
+    DeclineOfferButtonPressed,
+    CopyPgnButtonPressed,
+    EditPositionButtonPressed,
+    EditorBrushSelected(Option<(piece::Color, piece::PieceType)>),
+    EditorTurnSelected(piece::Color),
+    EditorCastlingToggled(usize, bool),
+    EditorEnPassantChanged(String),
+    ClearBoardButtonPressed,
+    StartingPositionButtonPressed,
+    CopyFenButtonPressed,
+    CancelEditButtonPressed,
+    PlayPositionButtonPressed,
+    FlipBoardButtonPressed,
+    MoveSelected(tree::NodeId),
+    CommentChanged(String),
+    NagToggled(u8),
+    PromoteVariationButtonPressed,
+    DeleteMoveButtonPressed,
+    PastePgnButtonPressed,
+    PgnPasted(Option<String>),
+    CoordinatesToggled(bool),
+    AnalysisButtonPressed,
+    EnginePathChanged(String),
+    NetworkAddressChanged(String),
+    HostButtonPressed,
+    JoinButtonPressed,
+    DisconnectButtonPressed,
+    ChatInputChanged(String),
+    SendChatButtonPressed,
+    EngineTick,
+    NetworkTick,
+    ClockTick(Instant),
+    AnimationTick(Instant),
+    StatusTick(Instant),
     WindowEventOccurred(iced::event::Event),
 }
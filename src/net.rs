@@ -0,0 +1,568 @@
+use crate::{
+    clock::{Stage, TimeControl, Timing},
+    piece::Color,
+    variant::VariantKind,
+};
+use std::{
+    fmt,
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+///The version of the protocol, which both players must speak.
+pub const PROTOCOL_VERSION: u32 = 1;
+///The port a game is hosted on when none is given.
+pub const DEFAULT_PORT: u16 = 7878;
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(3);
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(2);
+const ACCEPT_INTERVAL: Duration = Duration::from_millis(100);
+///The longest line read from the other player, which is far more than a long game needs.
+const MAX_LINE_LENGTH: u64 = 64 * 1024;
+
+///A message sent between two players, written on one line with its fields separated by tabs.
+#[derive(Debug, Clone, PartialEq)]
+pub enum NetMessage {
+    ///Opens a connection, with the protocol version the sender speaks.
+    Hello(u32),
+    ///Starts or resumes a game, with the side the receiver plays and the moves played so far
+    ///in UCI.
+    NewGame {
+        color: Color,
+        variant: VariantKind,
+        fen: String,
+        time_control: TimeControl,
+        moves: Vec<String>,
+    },
+    ///A move in UCI, such as `e2e4` or `N@f3`.
+    Move(String),
+    Resign,
+    DrawOffer,
+    ///Accepts the draw offered if true, or declines it.
+    DrawReply(bool),
+    TakebackRequest,
+    ///Accepts the takeback requested if true, or declines it.
+    TakebackReply(bool),
+    Chat(String),
+    ///White's and Black's time left, sent after every move so both clocks agree.
+    Clock(Duration, Duration),
+}
+
+///Why a line received from the other player couldn't be read.
+#[derive(Debug, Clone, PartialEq)]
+pub enum NetError {
+    UnknownMessage(String),
+    ///A field of the message is missing or can't be read.
+    InvalidField(&'static str),
+    ///The line is longer than any message, which closes the connection.
+    LineTooLong,
+}
+
+impl fmt::Display for NetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownMessage(kind) => write!(f, "Unknown message \"{kind}\""),
+            Self::InvalidField(field) => write!(f, "The message's {field} can't be read"),
+            Self::LineTooLong => write!(f, "The message is too long"),
+        }
+    }
+}
+
+impl NetMessage {
+    ///Writes the message as it is sent, without the line break.
+    pub fn to_line(&self) -> String {
+        let fields: Vec<String> = match self {
+            Self::Hello(version) => vec!["hello".to_string(), version.to_string()],
+            Self::NewGame {
+                color,
+                variant,
+                fen,
+                time_control,
+                moves,
+            } => vec![
+                "newgame".to_string(),
+                if *color == Color::White { "w" } else { "b" }.to_string(),
+                variant.pgn_name().to_string(),
+                fen.clone(),
+                write_time_control(time_control),
+                moves.join(" "),
+            ],
+            Self::Move(uci) => vec!["move".to_string(), uci.clone()],
+            Self::Resign => vec!["resign".to_string()],
+            Self::DrawOffer => vec!["draw".to_string(), "offer".to_string()],
+            Self::DrawReply(is_accepted) => vec!["draw".to_string(), reply(*is_accepted)],
+            Self::TakebackRequest => vec!["takeback".to_string(), "request".to_string()],
+            Self::TakebackReply(is_accepted) => {
+                vec!["takeback".to_string(), reply(*is_accepted)]
+            }
+            // Tabs and line breaks would end the field or the message early.
+            Self::Chat(text) => vec!["chat".to_string(), text.replace(['\t', '\n', '\r'], " ")],
+            Self::Clock(white, black) => vec![
+                "clock".to_string(),
+                white.as_millis().to_string(),
+                black.as_millis().to_string(),
+            ],
+        };
+        fields.join("\t")
+    }
+    ///Reads a message from a line received from the other player.
+    pub fn parse(line: &str) -> Result<Self, NetError> {
+        let fields: Vec<&str> = line.trim_end_matches(['\r', '\n']).split('\t').collect();
+        let field = |index: usize, name: &'static str| -> Result<&str, NetError> {
+            fields
+                .get(index)
+                .copied()
+                .ok_or(NetError::InvalidField(name))
+        };
+        let millis = |index: usize| -> Result<Duration, NetError> {
+            field(index, "time")?
+                .parse()
+                .map(Duration::from_millis)
+                .map_err(|_| NetError::InvalidField("time"))
+        };
+        match fields[0] {
+            "hello" => field(1, "version")?
+                .parse()
+                .map(Self::Hello)
+                .map_err(|_| NetError::InvalidField("version")),
+            "newgame" => Ok(Self::NewGame {
+                color: match field(1, "color")? {
+                    "w" => Color::White,
+                    "b" => Color::Black,
+                    _ => return Err(NetError::InvalidField("color")),
+                },
+                variant: VariantKind::from_pgn_name(field(2, "variant")?),
+                fen: field(3, "FEN")?.to_string(),
+                time_control: read_time_control(field(4, "time control")?)
+                    .ok_or(NetError::InvalidField("time control"))?,
+                moves: field(5, "moves")?
+                    .split_whitespace()
+                    .map(ToString::to_string)
+                    .collect(),
+            }),
+            "move" => Ok(Self::Move(field(1, "move")?.to_string())),
+            "resign" => Ok(Self::Resign),
+            "draw" => match field(1, "draw action")? {
+                "offer" => Ok(Self::DrawOffer),
+                answer => read_reply(answer).map(Self::DrawReply),
+            },
+            "takeback" => match field(1, "takeback action")? {
+                "request" => Ok(Self::TakebackRequest),
+                answer => read_reply(answer).map(Self::TakebackReply),
+            },
+            "chat" => Ok(Self::Chat(field(1, "text")?.to_string())),
+            "clock" => Ok(Self::Clock(millis(1)?, millis(2)?)),
+            kind => Err(NetError::UnknownMessage(kind.to_string())),
+        }
+    }
+}
+
+fn reply(is_accepted: bool) -> String {
+    if is_accepted { "accept" } else { "decline" }.to_string()
+}
+
+fn read_reply(answer: &str) -> Result<bool, NetError> {
+    match answer {
+        "accept" => Ok(true),
+        "decline" => Ok(false),
+        _ => Err(NetError::InvalidField("answer")),
+    }
+}
+
+///Writes a time control as its stages separated by spaces, each written as the moves (or `-`
+///for the rest of the game), seconds and timing separated by slashes, such as `40/5400/i30`.
+fn write_time_control(time_control: &TimeControl) -> String {
+    let stages: Vec<String> = time_control
+        .stages
+        .iter()
+        .map(|stage| {
+            let moves: String = stage
+                .moves
+                .map_or("-".to_string(), |moves| moves.to_string());
+            let timing: String = match stage.timing {
+                Timing::SuddenDeath => "s".to_string(),
+                Timing::Increment(time) => format!("i{}", time.as_secs()),
+                Timing::Bronstein(time) => format!("b{}", time.as_secs()),
+                Timing::Delay(time) => format!("d{}", time.as_secs()),
+            };
+            format!("{moves}/{}/{timing}", stage.time.as_secs())
+        })
+        .collect();
+    stages.join(" ")
+}
+
+fn read_time_control(text: &str) -> Option<TimeControl> {
+    let stages: Option<Vec<Stage>> = text
+        .split_whitespace()
+        .map(|stage| {
+            let mut parts = stage.split('/');
+            let moves: Option<u32> = match parts.next()? {
+                "-" => None,
+                moves => Some(moves.parse().ok()?),
+            };
+            let time = Duration::from_secs(parts.next()?.parse().ok()?);
+            let timing: &str = parts.next()?;
+            let extra = || timing.get(1..)?.parse().ok().map(Duration::from_secs);
+            let timing: Timing = match timing.chars().next()? {
+                's' => Timing::SuddenDeath,
+                'i' => Timing::Increment(extra()?),
+                'b' => Timing::Bronstein(extra()?),
+                'd' => Timing::Delay(extra()?),
+                _ => return None,
+            };
+            Some(Stage {
+                moves,
+                time,
+                timing,
+            })
+        })
+        .collect();
+    Some(TimeControl { stages: stages? })
+}
+
+///Something that happened on the connection to the other player.
+#[derive(Debug, Clone, PartialEq)]
+pub enum NetEvent {
+    Connected,
+    Disconnected,
+    Received(NetMessage),
+    ///A line was received that couldn't be read.
+    Error(NetError),
+    ///The other player speaks another version of the protocol, so no game can be played.
+    VersionMismatch(u32),
+}
+
+///How far along the connection to the other player is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkStatus {
+    ///Hosting a game with nobody connected yet.
+    Waiting,
+    Connected,
+    ///Trying to get back to a host that was lost.
+    Reconnecting,
+}
+
+impl fmt::Display for LinkStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Waiting => write!(f, "Waiting for an opponent to join"),
+            Self::Connected => write!(f, "Connected"),
+            Self::Reconnecting => write!(f, "Connection lost, reconnecting"),
+        }
+    }
+}
+
+///What the background threads share with the connection.
+struct Link {
+    status: LinkStatus,
+    events: Vec<NetEvent>,
+    ///The stream to the other player while connected, which messages are written to.
+    stream: Option<TcpStream>,
+}
+
+///A connection to the other player in a game over the network, run on background threads until
+///it is dropped. A guest reconnects to the host on its own if the connection is lost, and a host
+///waits for its guest to come back.
+pub struct Connection {
+    link: Arc<Mutex<Link>>,
+    stop: Arc<AtomicBool>,
+    ///The port being hosted on, or `None` when joined to a host.
+    port: Option<u16>,
+}
+
+impl Connection {
+    ///Hosts a game on the given port, waiting for another player to join.
+    pub fn host(port: u16) -> io::Result<Self> {
+        let listener = TcpListener::bind(("0.0.0.0", port))?;
+        let port: u16 = listener.local_addr()?.port();
+        listener.set_nonblocking(true)?;
+        let connection = Self::new(LinkStatus::Waiting, Some(port));
+        let link = Arc::clone(&connection.link);
+        let stop = Arc::clone(&connection.stop);
+        thread::spawn(move || {
+            while !stop.load(Ordering::Relaxed) {
+                match listener.accept() {
+                    Ok((stream, _)) => {
+                        // A new guest replaces one that is gone, such as the same player
+                        // coming back.
+                        if stream.set_nonblocking(false).is_ok() {
+                            let _ = run_stream(stream, &link, &stop);
+                            set_status(&link, LinkStatus::Waiting);
+                        }
+                    }
+                    Err(_) => thread::sleep(ACCEPT_INTERVAL),
+                }
+            }
+        });
+        Ok(connection)
+    }
+    ///Joins a game hosted at the given address, such as `192.168.1.20:7878`.
+    pub fn join(address: &str) -> io::Result<Self> {
+        let address: SocketAddr = address.to_socket_addrs()?.next().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "The address can't be found")
+        })?;
+        let stream = TcpStream::connect_timeout(&address, CONNECT_TIMEOUT)?;
+        let connection = Self::new(LinkStatus::Connected, None);
+        let link = Arc::clone(&connection.link);
+        let stop = Arc::clone(&connection.stop);
+        thread::spawn(move || {
+            let mut stream: Option<TcpStream> = Some(stream);
+            while !stop.load(Ordering::Relaxed) {
+                match stream.take() {
+                    Some(stream) => {
+                        let _ = run_stream(stream, &link, &stop);
+                        set_status(&link, LinkStatus::Reconnecting);
+                    }
+                    None => {
+                        thread::sleep(RECONNECT_INTERVAL);
+                        stream = TcpStream::connect_timeout(&address, CONNECT_TIMEOUT).ok();
+                    }
+                }
+            }
+        });
+        Ok(connection)
+    }
+    fn new(status: LinkStatus, port: Option<u16>) -> Self {
+        Self {
+            link: Arc::new(Mutex::new(Link {
+                status,
+                events: Vec::new(),
+                stream: None,
+            })),
+            stop: Arc::new(AtomicBool::new(false)),
+            port,
+        }
+    }
+    ///Sends a message to the other player, returning whether it could be sent.
+    pub fn send(&self, message: &NetMessage) -> bool {
+        let link = self.link.lock().unwrap();
+        let Some(mut stream) = link.stream.as_ref() else {
+            return false;
+        };
+        writeln!(stream, "{}", message.to_line()).is_ok()
+    }
+    ///Returns everything that happened since the last call.
+    pub fn events(&self) -> Vec<NetEvent> {
+        std::mem::take(&mut self.link.lock().unwrap().events)
+    }
+    pub fn status(&self) -> LinkStatus {
+        self.link.lock().unwrap().status
+    }
+    ///Returns the port being hosted on, or `None` when joined to a host.
+    pub fn port(&self) -> Option<u16> {
+        self.port
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        // Shutting the stream down wakes the thread blocked reading from it.
+        if let Some(stream) = &self.link.lock().unwrap().stream {
+            let _ = stream.shutdown(std::net::Shutdown::Both);
+        }
+    }
+}
+
+///Reads messages from a newly connected stream until it closes.
+fn run_stream(stream: TcpStream, link: &Mutex<Link>, stop: &AtomicBool) -> io::Result<()> {
+    {
+        let mut link = link.lock().unwrap();
+        link.stream = Some(stream.try_clone()?);
+        link.status = LinkStatus::Connected;
+        link.events.push(NetEvent::Connected);
+    }
+    let _ = stream.set_nodelay(true);
+    let mut reader = BufReader::new(stream);
+    loop {
+        let mut line: Vec<u8> = Vec::new();
+        match (&mut reader)
+            .take(MAX_LINE_LENGTH)
+            .read_until(b'\n', &mut line)
+        {
+            Ok(0) | Err(_) => break,
+            Ok(_) => (),
+        }
+        if stop.load(Ordering::Relaxed) {
+            break;
+        }
+        if !line.ends_with(b"\n") && line.len() as u64 == MAX_LINE_LENGTH {
+            let error = NetEvent::Error(NetError::LineTooLong);
+            link.lock().unwrap().events.push(error);
+            break;
+        }
+        let line = String::from_utf8_lossy(&line);
+        let event = match NetMessage::parse(&line) {
+            Ok(NetMessage::Hello(version)) if version != PROTOCOL_VERSION => {
+                NetEvent::VersionMismatch(version)
+            }
+            Ok(message) => NetEvent::Received(message),
+            Err(error) => NetEvent::Error(error),
+        };
+        link.lock().unwrap().events.push(event);
+    }
+    let mut link = link.lock().unwrap();
+    link.stream = None;
+    if !stop.load(Ordering::Relaxed) {
+        link.events.push(NetEvent::Disconnected);
+    }
+    Ok(())
+}
+
+fn set_status(link: &Mutex<Link>, status: LinkStatus) {
+    link.lock().unwrap().status = status;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    ///Collects the connection's events until the given number have arrived, or a few seconds
+    ///have passed.
+    fn wait_for_events(connection: &Connection, count: usize) -> Vec<NetEvent> {
+        let started = Instant::now();
+        let mut events: Vec<NetEvent> = Vec::new();
+        while events.len() < count && started.elapsed() < Duration::from_secs(5) {
+            events.extend(connection.events());
+            thread::sleep(Duration::from_millis(10));
+        }
+        events
+    }
+
+    #[test]
+    fn messages_round_trip() {
+        let messages = [
+            NetMessage::Hello(PROTOCOL_VERSION),
+            NetMessage::NewGame {
+                color: Color::Black,
+                variant: VariantKind::Crazyhouse,
+                fen: "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R[Nq] w KQkq - 2 3"
+                    .to_string(),
+                time_control: TimeControl::presets()[5].clone(),
+                moves: vec!["e2e4".to_string(), "N@f3".to_string(), "a7a8q".to_string()],
+            },
+            NetMessage::NewGame {
+                color: Color::White,
+                variant: VariantKind::ThreeCheck,
+                fen: "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2 +1+0"
+                    .to_string(),
+                time_control: TimeControl::default(),
+                moves: Vec::new(),
+            },
+            NetMessage::Move("e7e8n".to_string()),
+            NetMessage::Resign,
+            NetMessage::DrawOffer,
+            NetMessage::DrawReply(true),
+            NetMessage::TakebackRequest,
+            NetMessage::TakebackReply(false),
+            NetMessage::Chat("Good luck!".to_string()),
+            NetMessage::Clock(Duration::from_millis(61_250), Duration::from_secs(3)),
+        ];
+        for message in messages {
+            assert_eq!(NetMessage::parse(&message.to_line()), Ok(message));
+        }
+    }
+
+    #[test]
+    fn chat_tabs_and_line_breaks_stay_in_one_field() {
+        let line: String = NetMessage::Chat("a\tb\nc".to_string()).to_line();
+        assert!(!line.contains('\n'));
+        assert_eq!(
+            NetMessage::parse(&line),
+            Ok(NetMessage::Chat("a b c".to_string()))
+        );
+    }
+
+    #[test]
+    fn unreadable_lines_are_errors() {
+        assert_eq!(
+            NetMessage::parse("castle\tO-O"),
+            Err(NetError::UnknownMessage("castle".to_string()))
+        );
+        assert_eq!(
+            NetMessage::parse("hello\tone"),
+            Err(NetError::InvalidField("version"))
+        );
+        assert_eq!(
+            NetMessage::parse("newgame\tw\tStandard"),
+            Err(NetError::InvalidField("FEN"))
+        );
+    }
+
+    #[test]
+    fn other_protocol_version_is_reported() {
+        let host: Connection = Connection::host(0).unwrap();
+        let mut guest = TcpStream::connect(("127.0.0.1", host.port().unwrap())).unwrap();
+        writeln!(
+            guest,
+            "{}",
+            NetMessage::Hello(PROTOCOL_VERSION + 1).to_line()
+        )
+        .unwrap();
+        assert_eq!(
+            wait_for_events(&host, 2),
+            vec![
+                NetEvent::Connected,
+                NetEvent::VersionMismatch(PROTOCOL_VERSION + 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn overlong_line_closes_the_connection() {
+        let host: Connection = Connection::host(0).unwrap();
+        let mut guest = TcpStream::connect(("127.0.0.1", host.port().unwrap())).unwrap();
+        let line: String = "a".repeat(MAX_LINE_LENGTH as usize + 1);
+        let _ = guest.write_all(line.as_bytes());
+        assert_eq!(
+            wait_for_events(&host, 3),
+            vec![
+                NetEvent::Connected,
+                NetEvent::Error(NetError::LineTooLong),
+                NetEvent::Disconnected
+            ]
+        );
+    }
+
+    #[test]
+    fn host_and_guest_exchange_a_game() {
+        let host: Connection = Connection::host(0).unwrap();
+        let guest: Connection =
+            Connection::join(&format!("127.0.0.1:{}", host.port().unwrap())).unwrap();
+        assert_eq!(wait_for_events(&host, 1), vec![NetEvent::Connected]);
+        assert_eq!(wait_for_events(&guest, 1), vec![NetEvent::Connected]);
+        assert_eq!(host.status(), LinkStatus::Connected);
+
+        let new_game = NetMessage::NewGame {
+            color: Color::Black,
+            variant: VariantKind::Standard,
+            fen: "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1".to_string(),
+            time_control: TimeControl::presets()[2].clone(),
+            moves: vec!["e2e4".to_string()],
+        };
+        assert!(host.send(&NetMessage::Hello(PROTOCOL_VERSION)));
+        assert!(host.send(&new_game));
+        assert!(guest.send(&NetMessage::Hello(PROTOCOL_VERSION)));
+        assert!(guest.send(&NetMessage::Move("e7e5".to_string())));
+        assert_eq!(
+            wait_for_events(&guest, 2),
+            vec![
+                NetEvent::Received(NetMessage::Hello(PROTOCOL_VERSION)),
+                NetEvent::Received(new_game)
+            ]
+        );
+        assert_eq!(
+            wait_for_events(&host, 2),
+            vec![
+                NetEvent::Received(NetMessage::Hello(PROTOCOL_VERSION)),
+                NetEvent::Received(NetMessage::Move("e7e5".to_string()))
+            ]
+        );
+    }
+}
@@ -0,0 +1,84 @@
+use iced::{
+    mouse,
+    widget::{
+        canvas::{self, Path, Stroke},
+        svg::Handle,
+    },
+    Color, Point, Rectangle, Renderer, Size, Theme, Vector,
+};
+
+///Draws things floating above the board, such as a dragged piece or arrows drawn by a user.
+#[derive(Default)]
+pub struct BoardOverlay {
+    floating_pieces: Vec<(Handle, Rectangle)>,
+    arrows: Vec<(Point, Point, Color, f32)>,
+    circles: Vec<(Point, f32, Color)>,
+}
+
+impl BoardOverlay {
+    ///Adds a piece drawn centered on the given point.
+    pub fn push_piece(&mut self, image: Handle, center: Point, size: Size) {
+        let side = size.width.min(size.height);
+        let top_left = Point::new(center.x - side / 2.0, center.y - side / 2.0);
+        self.floating_pieces
+            .push((image, Rectangle::new(top_left, Size::new(side, side))));
+    }
+    ///Adds an arrow of the given thickness pointing from one point to another.
+    pub fn push_arrow(&mut self, start: Point, end: Point, color: Color, width: f32) {
+        self.arrows.push((start, end, color, width));
+    }
+    ///Adds a ring around the given point.
+    pub fn push_circle(&mut self, center: Point, radius: f32, color: Color) {
+        self.circles.push((center, radius, color));
+    }
+}
+
+impl<Message> canvas::Program<Message> for BoardOverlay {
+    type State = ();
+
+    fn draw(
+        &self,
+        _state: &Self::State,
+        renderer: &Renderer,
+        _theme: &Theme,
+        bounds: Rectangle,
+        _cursor: mouse::Cursor,
+    ) -> Vec<canvas::Geometry> {
+        let mut frame = canvas::Frame::new(renderer, bounds.size());
+        for (center, radius, color) in &self.circles {
+            frame.stroke(
+                &Path::circle(*center, *radius),
+                Stroke::default()
+                    .with_color(*color)
+                    .with_width(radius / 8.0),
+            );
+        }
+        for (start, end, color, width) in &self.arrows {
+            let length = start.distance(*end);
+            if length == 0.0 {
+                continue;
+            }
+            let direction = (*end - *start) * (1.0 / length);
+            let normal = Vector::new(-direction.y, direction.x);
+            let head_length = (width * 2.5).min(length);
+            let head_base = *end - direction * head_length;
+            frame.stroke(
+                &Path::line(*start, head_base),
+                Stroke::default().with_color(*color).with_width(*width),
+            );
+            frame.fill(
+                &Path::new(|builder| {
+                    builder.move_to(*end);
+                    builder.line_to(head_base + normal * *width * 1.5);
+                    builder.line_to(head_base - normal * *width * 1.5);
+                    builder.close();
+                }),
+                *color,
+            );
+        }
+        for (image, piece_bounds) in &self.floating_pieces {
+            frame.draw_svg(*piece_bounds, image);
+        }
+        vec![frame.into_geometry()]
+    }
+}
@@ -0,0 +1,442 @@
+use crate::{
+    board::{Board, FenError},
+    piece::{Color, Position},
+    tree::{Marking, MarkingColor, MoveTree, NodeId},
+    variant::VariantKind,
+};
+use std::{
+    fmt,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+const LINE_LENGTH: usize = 80;
+
+///The NAGs that can be added in the side panel, with the glyphs shown for them.
+pub const NAG_GLYPHS: [(u8, &str); 14] = [
+    (1, "!"),
+    (2, "?"),
+    (3, "!!"),
+    (4, "??"),
+    (5, "!?"),
+    (6, "?!"),
+    (10, "="),
+    (13, "∞"),
+    (14, "+="),
+    (15, "=+"),
+    (16, "+/-"),
+    (17, "-/+"),
+    (18, "+-"),
+    (19, "-+"),
+];
+
+///The tag pairs of a game, kept in the order they are written.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Tags(Vec<(String, String)>);
+
+impl Tags {
+    ///Returns the Seven Tag Roster with unknown values.
+    pub fn new() -> Self {
+        let mut tags = Self::default();
+        for (name, value) in [
+            ("Event", "Casual Game"),
+            ("Site", "?"),
+            ("Date", "????.??.??"),
+            ("Round", "-"),
+            ("White", "?"),
+            ("Black", "?"),
+            ("Result", "*"),
+        ] {
+            tags.set(name, value);
+        }
+        tags
+    }
+    ///Sets the value of a tag, adding it at the end if it isn't there yet.
+    pub fn set(&mut self, name: &str, value: impl Into<String>) {
+        let value: String = value.into();
+        match self.0.iter_mut().find(|(tag, _)| tag == name) {
+            Some((_, old_value)) => *old_value = value,
+            None => self.0.push((name.to_string(), value)),
+        }
+    }
+    ///Returns the value of a tag.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|(tag, _)| tag == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+///Why a PGN couldn't be read.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PgnError {
+    InvalidTag(String),
+    InvalidFen(FenError),
+    IllegalMove(String),
+    UnbalancedParentheses,
+}
+
+impl fmt::Display for PgnError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidTag(line) => write!(f, "Couldn't read the tag {line}"),
+            Self::InvalidFen(error) => write!(f, "Couldn't read the FEN tag: {error}"),
+            Self::IllegalMove(notation) => write!(f, "The move {notation} isn't legal"),
+            Self::UnbalancedParentheses => write!(f, "The variations aren't closed properly"),
+        }
+    }
+}
+
+///Writes a game as PGN, with its variations in parentheses.
+pub fn write_pgn(tags: &Tags, tree: &MoveTree) -> String {
+    let mut pgn = String::new();
+    for (name, value) in &tags.0 {
+        let value: String = value.replace('\\', "\\\\").replace('"', "\\\"");
+        pgn.push_str(&format!("[{name} \"{value}\"]\n"));
+    }
+    pgn.push('\n');
+
+    let mut movetext = String::new();
+    push_comment(tree, MoveTree::ROOT, &mut movetext);
+    write_line(tree, MoveTree::ROOT, true, &mut movetext);
+    push_token(&mut movetext, tags.get("Result").unwrap_or("*"));
+    let mut line_length: usize = 0;
+    for token in movetext.split_whitespace() {
+        if line_length > 0 && line_length + token.len() + 1 > LINE_LENGTH {
+            pgn.push('\n');
+            line_length = 0;
+        } else if line_length > 0 {
+            pgn.push(' ');
+            line_length += 1;
+        }
+        pgn.push_str(token);
+        line_length += token.len();
+    }
+    pgn.push('\n');
+    pgn
+}
+
+///Writes the moves after a node, following the main continuation and putting the other moves
+///in parentheses.
+fn write_line(tree: &MoveTree, mut node: NodeId, mut needs_number: bool, movetext: &mut String) {
+    while let Some((main, variations)) = tree.children(node).split_first() {
+        push_move(tree, *main, needs_number, movetext);
+        needs_number = has_comment(tree, *main);
+        for variation in variations {
+            push_token(movetext, "(");
+            push_move(tree, *variation, true, movetext);
+            write_line(tree, *variation, has_comment(tree, *variation), movetext);
+            push_token(movetext, ")");
+            needs_number = true;
+        }
+        node = *main;
+    }
+}
+
+///Writes a move with its move number, NAGs and comment. Black's moves only need a number after
+///a break in the line.
+fn push_move(tree: &MoveTree, node: NodeId, needs_number: bool, movetext: &mut String) {
+    let board: &Board = tree.board(tree.parent(node).unwrap_or(MoveTree::ROOT));
+    if board.turn == Color::White {
+        push_token(movetext, &format!("{}.", board.fullmove_number));
+    } else if needs_number {
+        push_token(movetext, &format!("{}...", board.fullmove_number));
+    }
+    push_token(movetext, tree.notation(node));
+    for nag in tree.nags(node) {
+        push_token(movetext, &format!("${nag}"));
+    }
+    push_comment(tree, node, movetext);
+}
+
+///Writes the comment on a node, with its arrows and circled squares as `%cal` and `%csl`
+///commands.
+fn push_comment(tree: &MoveTree, node: NodeId, movetext: &mut String) {
+    if !has_comment(tree, node) {
+        return;
+    }
+    let mut circles: Vec<String> = Vec::new();
+    let mut arrows: Vec<String> = Vec::new();
+    for marking in tree.markings(node) {
+        match marking {
+            Marking::Circle(color, square) => circles.push(format!("{}{square}", color.letter())),
+            Marking::Arrow(color, start, end) => {
+                arrows.push(format!("{}{start}{end}", color.letter()));
+            }
+        }
+    }
+    let mut comment = String::new();
+    if !circles.is_empty() {
+        comment.push_str(&format!("[%csl {}]", circles.join(",")));
+    }
+    if !arrows.is_empty() {
+        comment.push_str(&format!("[%cal {}]", arrows.join(",")));
+    }
+    let text: String = tree.comment(node).trim().replace('}', "");
+    if !comment.is_empty() && !text.is_empty() {
+        comment.push(' ');
+    }
+    comment.push_str(&text);
+    push_token(movetext, &format!("{{{comment}}}"));
+}
+
+fn has_comment(tree: &MoveTree, node: NodeId) -> bool {
+    !tree.comment(node).trim().is_empty() || !tree.markings(node).is_empty()
+}
+
+///Takes the `%cal` and `%csl` commands out of a comment, returning the rest of the comment and
+///the arrows and circled squares they draw.
+fn read_markings(comment: &str) -> (String, Vec<Marking>) {
+    let mut text = String::new();
+    let mut markings: Vec<Marking> = Vec::new();
+    let mut rest: &str = comment;
+    while let Some(start) = rest.find("[%") {
+        text.push_str(&rest[..start]);
+        let Some(length) = rest[start..].find(']') else {
+            rest = &rest[start..];
+            break;
+        };
+        let command: &str = &rest[start + 2..start + length];
+        rest = &rest[start + length + 1..];
+        let (name, arguments) = command.split_once(' ').unwrap_or((command, ""));
+        let is_arrow: bool = match name {
+            "cal" => true,
+            "csl" => false,
+            _ => {
+                text.push_str(&format!("[%{command}]"));
+                continue;
+            }
+        };
+        for argument in arguments.split(',').map(str::trim) {
+            let color: Option<MarkingColor> =
+                argument.chars().next().and_then(MarkingColor::from_letter);
+            let square = |range: std::ops::Range<usize>| {
+                argument.get(range).and_then(Position::from_algebraic)
+            };
+            let marking: Option<Marking> = if is_arrow && argument.len() == 5 {
+                color
+                    .zip(square(1..3).zip(square(3..5)))
+                    .map(|(color, (start, end))| Marking::Arrow(color, start, end))
+            } else if !is_arrow && argument.len() == 3 {
+                color
+                    .zip(square(1..3))
+                    .map(|(color, square)| Marking::Circle(color, square))
+            } else {
+                None
+            };
+            markings.extend(marking);
+        }
+    }
+    text.push_str(rest);
+    (text, markings)
+}
+
+fn push_token(movetext: &mut String, token: &str) {
+    if !movetext.is_empty() && !movetext.ends_with('(') && token != ")" {
+        movetext.push(' ');
+    }
+    movetext.push_str(token);
+}
+
+///Reads the first game in a PGN, with its variations.
+pub fn read_pgn(pgn: &str) -> Result<(Tags, MoveTree), PgnError> {
+    let mut tags = Tags::default();
+    let mut movetext = String::new();
+    for line in pgn.lines() {
+        let line: &str = line.trim();
+        if line.starts_with('%') {
+            continue;
+        }
+        if let Some(tag) = line.strip_prefix('[') {
+            let (name, value) = parse_tag(tag).ok_or(PgnError::InvalidTag(line.to_string()))?;
+            tags.set(name, value);
+        } else {
+            movetext.push_str(line);
+            movetext.push('\n');
+        }
+    }
+
+    let variant = VariantKind::from_pgn_name(tags.get("Variant").unwrap_or_default());
+    let mut board: Board = match tags.get("FEN") {
+        Some(fen) => Board::from_variant_fen(fen, variant).map_err(PgnError::InvalidFen)?,
+        // A Chess960 game without a FEN tag starts from the standard position, one of the 960.
+        None if variant == VariantKind::Chess960 => Board::default(),
+        None => variant.rules().starting_board(),
+    };
+    board.variant = variant;
+    let mut tree = MoveTree::new(board);
+    let mut node: NodeId = MoveTree::ROOT;
+    let mut variation_starts: Vec<NodeId> = Vec::new();
+    let mut chars = movetext.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' => {
+                let mut comment = String::new();
+                while let Some(c) = chars.next_if(|c| *c != '}') {
+                    comment.push(c);
+                }
+                chars.next();
+                let (comment, markings) = read_markings(&comment);
+                for marking in markings {
+                    tree.toggle_marking(node, marking);
+                }
+                // A comment before a variation's first move is kept with the move before it.
+                let comment: String = [tree.comment(node), comment.trim()].join(" ");
+                tree.set_comment(node, comment.trim().to_string());
+            }
+            ';' => while chars.next().is_some_and(|c| c != '\n') {},
+            '(' => {
+                variation_starts.push(node);
+                node = tree.parent(node).ok_or(PgnError::UnbalancedParentheses)?;
+            }
+            ')' => {
+                node = variation_starts
+                    .pop()
+                    .ok_or(PgnError::UnbalancedParentheses)?
+            }
+            c if c.is_whitespace() => (),
+            c => {
+                let mut token = String::from(c);
+                while let Some(next) =
+                    chars.next_if(|c| !c.is_whitespace() && !"(){};".contains(*c))
+                {
+                    token.push(next);
+                }
+                // Move numbers can be written right before the move, such as `1.e4`.
+                let notation: &str = match token.rfind('.') {
+                    Some(i) if token[..i].chars().all(|c| c.is_ascii_digit() || c == '.') => {
+                        &token[i + 1..]
+                    }
+                    _ => &token,
+                };
+                if let Some(nag) = notation.strip_prefix('$') {
+                    if let Ok(nag) = nag.parse() {
+                        tree.add_nag(node, nag);
+                    }
+                    continue;
+                }
+                if notation.is_empty()
+                    || matches!(token.as_str(), "1-0" | "0-1" | "1/2-1/2" | "*" | "e.p.")
+                {
+                    continue;
+                }
+                // Glyphs written right after the move, such as `e4!`, are the first six NAGs.
+                let glyph: &str = notation.trim_start_matches(|c: char| c != '!' && c != '?');
+                let notation: &str = &notation[..notation.len() - glyph.len()];
+                let mut board: Board = tree.board(node).clone();
+                let notation: String = board
+                    .play_san(notation)
+                    .ok_or(PgnError::IllegalMove(token.clone()))?;
+                node = tree.add_move(node, notation, board);
+                if let Some((nag, _)) = NAG_GLYPHS[..6].iter().find(|(_, symbol)| *symbol == glyph)
+                {
+                    tree.add_nag(node, *nag);
+                }
+            }
+        }
+    }
+    if !variation_starts.is_empty() {
+        return Err(PgnError::UnbalancedParentheses);
+    }
+    Ok((tags, tree))
+}
+
+///Reads the name and value of a tag pair, such as `Event "Casual Game"]`.
+fn parse_tag(tag: &str) -> Option<(&str, String)> {
+    let (name, rest) = tag.split_once(char::is_whitespace)?;
+    let mut chars = rest
+        .trim()
+        .strip_suffix(']')?
+        .trim_end()
+        .strip_prefix('"')?
+        .chars();
+    let mut value = String::new();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => value.push(chars.next()?),
+            '"' => return chars.as_str().is_empty().then_some((name, value)),
+            c => value.push(c),
+        }
+    }
+    None
+}
+
+///Returns a move with the glyphs of its NAGs, such as `e4!? +=`.
+pub fn annotate(notation: &str, nags: &[u8]) -> String {
+    let mut annotated = notation.to_string();
+    for nag in nags {
+        match NAG_GLYPHS.iter().find(|(glyph_nag, _)| glyph_nag == nag) {
+            Some((1..=6, glyph)) => annotated.push_str(glyph),
+            Some((_, glyph)) => annotated.push_str(&format!(" {glyph}")),
+            None => annotated.push_str(&format!(" ${nag}")),
+        }
+    }
+    annotated
+}
+
+///Joins moves in SAN with move numbers, starting at the given move number and side.
+pub fn format_moves(moves: &[String], mut move_number: u32, mut turn: Color) -> String {
+    let mut formatted_moves = String::new();
+    for (i, notation) in moves.iter().enumerate() {
+        if turn == Color::White {
+            formatted_moves.push_str(&format!("{move_number}. "));
+        } else if i == 0 {
+            formatted_moves.push_str(&format!("{move_number}... "));
+        }
+        formatted_moves.push_str(notation);
+        formatted_moves.push(' ');
+        if turn == Color::Black {
+            move_number += 1;
+        }
+        turn = !turn;
+    }
+    formatted_moves.trim_end().to_string()
+}
+
+///Returns today's date in the PGN format, such as `2024.03.09`.
+pub fn today() -> String {
+    let days = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs() / 86_400) as i64;
+    // Converts days since 1970-01-01 to a civil date, from Howard Hinnant's algorithm.
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{year:04}.{month:02}.{day:02}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn en_passant_and_promotions_round_trip() {
+        let pgn =
+            "[FEN \"4k3/1P1p4/8/4P3/8/8/8/4K3 b - - 0 1\"]\n\n1... d5 2. exd6 e.p. Kf7 3. b8=N *";
+        let (tags, tree) = read_pgn(pgn).unwrap();
+        let end: NodeId = tree.line_end(MoveTree::ROOT);
+        assert_eq!(tree.notation(end), "b8=N");
+        assert_eq!(
+            tree.notation(tree.parent(tree.parent(end).unwrap()).unwrap()),
+            "exd6"
+        );
+        let written: String = write_pgn(&tags, &tree);
+        assert!(written.contains("1... d5 2. exd6 Kf7 3. b8=N"), "{written}");
+        let (_, reread) = read_pgn(&written).unwrap();
+        assert_eq!(
+            reread.board(reread.line_end(MoveTree::ROOT)).to_fen(),
+            tree.board(end).to_fen()
+        );
+    }
+}
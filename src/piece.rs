@@ -1,4 +1,4 @@
-use std::ops::Not;
+use std::{fmt, ops::Not};
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum Color {
@@ -27,6 +27,22 @@ impl Position {
     pub fn new(x: u8, y: u8) -> Self {
         Self { x, y }
     }
+    ///Returns the position of a square written in algebraic notation, such as `e4`.
+    pub fn from_algebraic(square: &str) -> Option<Self> {
+        let mut chars = square.chars();
+        let file = chars.next()?;
+        let rank = chars.next()?.to_digit(10)?;
+        if chars.next().is_some() || !('a'..='h').contains(&file) || !(1..=8).contains(&rank) {
+            return None;
+        }
+        Some(Self::new(file as u8 - 97, 8 - rank as u8))
+    }
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", (self.x + 97) as char, 8 - self.y)
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Copy)]
@@ -46,6 +62,9 @@ pub struct Piece {
     pub position: Position,
     pub color: Color,
     pub has_moved: bool,
+    ///Whether the piece was a pawn that promoted, which goes back to being a pawn when it is
+    ///captured in Crazyhouse.
+    pub promoted: bool,
 }
 
 impl Piece {
@@ -56,6 +75,41 @@ impl Piece {
             position,
             color,
             has_moved: false,
+            promoted: false,
+        }
+    }
+    ///Creates a piece from its letter in FEN, uppercase for White and lowercase for Black.
+    pub fn from_fen_char(letter: char, position: Position) -> Option<Self> {
+        let piece_type = match letter.to_ascii_uppercase() {
+            'P' => PieceType::Pawn,
+            'N' => PieceType::Knight,
+            'B' => PieceType::Bishop,
+            'R' => PieceType::Rook,
+            'Q' => PieceType::Queen,
+            'K' => PieceType::King,
+            _ => return None,
+        };
+        let color = if letter.is_ascii_uppercase() {
+            Color::White
+        } else {
+            Color::Black
+        };
+        Some(Self::new(piece_type, position, color))
+    }
+    ///Returns the letter of the piece in FEN, uppercase for White and lowercase for Black.
+    pub fn fen_char(&self) -> char {
+        let letter = match self.piece_type {
+            PieceType::None => ' ',
+            PieceType::Pawn => 'P',
+            PieceType::Knight => 'N',
+            PieceType::Bishop => 'B',
+            PieceType::Rook => 'R',
+            PieceType::Queen => 'Q',
+            PieceType::King => 'K',
+        };
+        match self.color {
+            Color::White => letter,
+            Color::Black => letter.to_ascii_lowercase(),
         }
     }
 }
@@ -0,0 +1,183 @@
+use crate::{
+    board::{Board, FenError},
+    clock::TimeControl,
+    pgn::{today, Tags},
+    piece::Color,
+    variant::VariantKind,
+};
+use std::fmt;
+
+///Who makes the moves for one side.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlayerKind {
+    Human,
+    Engine,
+    ///The other player in a game over the network.
+    Remote,
+}
+
+impl PlayerKind {
+    ///The kinds offered in the new game dialog, which leaves out `Remote` since it is only set
+    ///by hosting or joining a game.
+    pub const ALL: [Self; 2] = [Self::Human, Self::Engine];
+}
+
+impl fmt::Display for PlayerKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Human => write!(f, "Human"),
+            Self::Engine => write!(f, "Engine"),
+            Self::Remote => write!(f, "Remote"),
+        }
+    }
+}
+
+///Why the board for a new game couldn't be set up.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SetupError {
+    InvalidFen(FenError),
+    InvalidChess960Number,
+}
+
+impl fmt::Display for SetupError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidFen(error) => write!(f, "{error}"),
+            Self::InvalidChess960Number => {
+                write!(f, "The Chess960 position number must be from 0 to 959")
+            }
+        }
+    }
+}
+
+///Where a new game starts from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StartingPosition {
+    Standard,
+    Fen,
+}
+
+impl StartingPosition {
+    pub const ALL: [Self; 2] = [Self::Standard, Self::Fen];
+}
+
+impl fmt::Display for StartingPosition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Standard => write!(f, "Standard"),
+            Self::Fen => write!(f, "From FEN"),
+        }
+    }
+}
+
+///The choices made in the new game dialog.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GameSetup {
+    pub white_name: String,
+    pub black_name: String,
+    pub white_player: PlayerKind,
+    pub black_player: PlayerKind,
+    pub engine_level: u32,
+    pub time_control: TimeControl,
+    pub starting_position: StartingPosition,
+    pub fen: String,
+    pub variant: VariantKind,
+    ///The number of the Chess960 starting position, or blank for a random one.
+    pub chess960_number: String,
+}
+
+impl Default for GameSetup {
+    fn default() -> Self {
+        Self {
+            white_name: String::new(),
+            black_name: String::new(),
+            white_player: PlayerKind::Human,
+            black_player: PlayerKind::Human,
+            engine_level: 2,
+            time_control: TimeControl::default(),
+            starting_position: StartingPosition::Standard,
+            fen: String::new(),
+            variant: VariantKind::Standard,
+            chess960_number: String::new(),
+        }
+    }
+}
+
+impl GameSetup {
+    ///Returns who plays the given side.
+    pub fn player(&self, color: Color) -> PlayerKind {
+        match color {
+            Color::White => self.white_player,
+            Color::Black => self.black_player,
+        }
+    }
+    ///Sets who plays the given side.
+    pub fn set_player(&mut self, color: Color, player: PlayerKind) {
+        match color {
+            Color::White => self.white_player = player,
+            Color::Black => self.black_player = player,
+        }
+    }
+    ///Returns the board the game starts from, which is the variant's own starting position
+    ///unless a FEN or a numbered Chess960 position was chosen.
+    pub fn board(&self) -> Result<Board, SetupError> {
+        let mut board: Board = match self.starting_position {
+            StartingPosition::Standard
+                if self.variant == VariantKind::Chess960
+                    && !self.chess960_number.trim().is_empty() =>
+            {
+                let number: u16 = self
+                    .chess960_number
+                    .trim()
+                    .parse()
+                    .ok()
+                    .filter(|number| *number < 960)
+                    .ok_or(SetupError::InvalidChess960Number)?;
+                Board::chess960(number)
+            }
+            StartingPosition::Standard => self.variant.rules().starting_board(),
+            StartingPosition::Fen => {
+                Board::from_variant_fen(&self.fen, self.variant).map_err(SetupError::InvalidFen)?
+            }
+        };
+        board.variant = self.variant;
+        Ok(board)
+    }
+    ///Returns the PGN tags describing the game, naming engine players after `engine_name`.
+    pub fn tags(&self, engine_name: &str) -> Tags {
+        let mut tags = Tags::new();
+        tags.set("Date", today());
+        for (color, name) in [
+            (Color::White, &self.white_name),
+            (Color::Black, &self.black_name),
+        ] {
+            let (name_tag, type_tag) = match color {
+                Color::White => ("White", "WhiteType"),
+                Color::Black => ("Black", "BlackType"),
+            };
+            let player = self.player(color);
+            let name = match (name.trim(), player) {
+                ("", PlayerKind::Engine) => format!("{engine_name} (Level {})", self.engine_level),
+                ("", PlayerKind::Human | PlayerKind::Remote) => "?".to_string(),
+                (name, _) => name.to_string(),
+            };
+            tags.set(name_tag, name);
+            tags.set(
+                type_tag,
+                match player {
+                    PlayerKind::Human | PlayerKind::Remote => "human",
+                    PlayerKind::Engine => "program",
+                },
+            );
+        }
+        tags.set("TimeControl", self.time_control.pgn_tag());
+        if self.variant != VariantKind::Standard {
+            tags.set("Variant", self.variant.pgn_name());
+        }
+        if self.starting_position == StartingPosition::Fen {
+            tags.set("SetUp", "1");
+            tags.set("FEN", self.fen.trim());
+        }
+        tags
+    }
+}
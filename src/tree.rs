@@ -0,0 +1,295 @@
+use crate::{board::Board, piece::Position};
+
+///Points to a move in a `MoveTree`.
+pub type NodeId = usize;
+
+///The colors arrows and circled squares can be drawn in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MarkingColor {
+    Green,
+    Red,
+    Yellow,
+    Blue,
+}
+
+impl MarkingColor {
+    ///Returns the color from its letter in the PGN `%cal` and `%csl` commands.
+    pub fn from_letter(letter: char) -> Option<Self> {
+        match letter {
+            'G' => Some(Self::Green),
+            'R' => Some(Self::Red),
+            'Y' => Some(Self::Yellow),
+            'B' => Some(Self::Blue),
+            _ => None,
+        }
+    }
+    pub fn letter(self) -> char {
+        match self {
+            Self::Green => 'G',
+            Self::Red => 'R',
+            Self::Yellow => 'Y',
+            Self::Blue => 'B',
+        }
+    }
+}
+
+///An arrow or circled square drawn on the board by a user.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Marking {
+    Arrow(MarkingColor, Position, Position),
+    Circle(MarkingColor, Position),
+}
+
+impl Marking {
+    pub fn color(self) -> MarkingColor {
+        match self {
+            Self::Arrow(color, _, _) | Self::Circle(color, _) => color,
+        }
+    }
+    ///Checks if both markings are on the same squares, whatever their colors.
+    fn is_on_same_squares(self, other: Self) -> bool {
+        match (self, other) {
+            (Self::Arrow(_, start, end), Self::Arrow(_, other_start, other_end)) => {
+                start == other_start && end == other_end
+            }
+            (Self::Circle(_, square), Self::Circle(_, other_square)) => square == other_square,
+            _ => false,
+        }
+    }
+}
+
+///One move in the tree, with the position it leads to.
+#[derive(Clone)]
+struct Node {
+    notation: String,
+    board: Board,
+    ///The comment written after the move, or about the game on the root.
+    comment: String,
+    nags: Vec<u8>,
+    ///The arrows and circled squares drawn on the position after the move.
+    markings: Vec<Marking>,
+    parent: Option<NodeId>,
+    ///The moves played from this position, with the main continuation first.
+    children: Vec<NodeId>,
+}
+
+///The moves of a game and all its variations, starting from the root position.
+#[derive(Clone)]
+pub struct MoveTree {
+    nodes: Vec<Node>,
+}
+
+impl Default for MoveTree {
+    fn default() -> Self {
+        Self::new(Board::default())
+    }
+}
+
+impl MoveTree {
+    ///The node holding the starting position, which has no move.
+    pub const ROOT: NodeId = 0;
+
+    ///Creates a tree with no moves, starting from the given board.
+    pub fn new(board: Board) -> Self {
+        Self {
+            nodes: vec![Node {
+                notation: String::new(),
+                board,
+                comment: String::new(),
+                nags: Vec::new(),
+                markings: Vec::new(),
+                parent: None,
+                children: Vec::new(),
+            }],
+        }
+    }
+    ///Adds a move after the given node and returns it, or returns the existing node if the move
+    ///was already played there.
+    pub fn add_move(&mut self, parent: NodeId, notation: String, board: Board) -> NodeId {
+        if let Some(node) = self.nodes[parent]
+            .children
+            .iter()
+            .find(|child| self.nodes[**child].notation == notation)
+        {
+            return *node;
+        }
+        let node: NodeId = self.nodes.len();
+        self.nodes.push(Node {
+            notation,
+            board,
+            comment: String::new(),
+            nags: Vec::new(),
+            markings: Vec::new(),
+            parent: Some(parent),
+            children: Vec::new(),
+        });
+        self.nodes[parent].children.push(node);
+        node
+    }
+    ///Returns the move in SAN that leads to the node.
+    pub fn notation(&self, node: NodeId) -> &str {
+        &self.nodes[node].notation
+    }
+    ///Returns the position after the node's move.
+    pub fn board(&self, node: NodeId) -> &Board {
+        &self.nodes[node].board
+    }
+    ///Returns the comment on the node.
+    pub fn comment(&self, node: NodeId) -> &str {
+        &self.nodes[node].comment
+    }
+    pub fn set_comment(&mut self, node: NodeId, comment: String) {
+        self.nodes[node].comment = comment;
+    }
+    ///Returns the Numeric Annotation Glyphs on the node, such as 1 for a good move.
+    pub fn nags(&self, node: NodeId) -> &[u8] {
+        &self.nodes[node].nags
+    }
+    ///Adds a NAG to the node, replacing any other of the same kind, so a move can't be both good
+    ///and bad.
+    pub fn add_nag(&mut self, node: NodeId, nag: u8) {
+        let nags: &mut Vec<u8> = &mut self.nodes[node].nags;
+        nags.retain(|old_nag| nag_group(*old_nag) != nag_group(nag));
+        nags.push(nag);
+        nags.sort_unstable();
+    }
+    ///Adds a NAG to the node, or removes it if the node already has it.
+    pub fn toggle_nag(&mut self, node: NodeId, nag: u8) {
+        if self.nodes[node].nags.contains(&nag) {
+            self.nodes[node].nags.retain(|old_nag| *old_nag != nag);
+        } else {
+            self.add_nag(node, nag);
+        }
+    }
+    pub fn markings(&self, node: NodeId) -> &[Marking] {
+        &self.nodes[node].markings
+    }
+    ///Adds an arrow or circle to the node. Drawing one again in the same color removes it, and
+    ///in another color changes its color.
+    pub fn toggle_marking(&mut self, node: NodeId, marking: Marking) {
+        let markings: &mut Vec<Marking> = &mut self.nodes[node].markings;
+        match markings
+            .iter()
+            .position(|old_marking| old_marking.is_on_same_squares(marking))
+        {
+            Some(index) if markings[index] == marking => {
+                markings.remove(index);
+            }
+            Some(index) => markings[index] = marking,
+            None => markings.push(marking),
+        }
+    }
+    ///Returns the node before the given one, or `None` for the root.
+    pub fn parent(&self, node: NodeId) -> Option<NodeId> {
+        self.nodes[node].parent
+    }
+    ///Returns the moves played after the node, with the main continuation first.
+    pub fn children(&self, node: NodeId) -> &[NodeId] {
+        &self.nodes[node].children
+    }
+    ///Returns the last node reached by following the main continuation from the given node.
+    pub fn line_end(&self, mut node: NodeId) -> NodeId {
+        while let Some(child) = self.nodes[node].children.first() {
+            node = *child;
+        }
+        node
+    }
+    ///Checks if the node is on the mainline of the game.
+    pub fn is_mainline(&self, mut node: NodeId) -> bool {
+        while let Some(parent) = self.nodes[node].parent {
+            if self.nodes[parent].children.first() != Some(&node) {
+                return false;
+            }
+            node = parent;
+        }
+        true
+    }
+    ///Makes the line leading to the node the mainline.
+    pub fn promote(&mut self, mut node: NodeId) {
+        while let Some(parent) = self.nodes[node].parent {
+            let children: &mut Vec<NodeId> = &mut self.nodes[parent].children;
+            if let Some(index) = children.iter().position(|child| *child == node) {
+                children.remove(index);
+                children.insert(0, node);
+            }
+            node = parent;
+        }
+    }
+    ///Removes the node and every move after it, returning the node before it. The root can't be
+    ///removed.
+    ///
+    ///Nodes added after the removed ones move down to fill the gap, so any other node kept from
+    ///before has to be looked up again. The node returned keeps its place, as every node comes
+    ///after the one before it.
+    pub fn delete(&mut self, node: NodeId) -> Option<NodeId> {
+        let parent: NodeId = self.nodes[node].parent?;
+        self.nodes[parent].children.retain(|child| *child != node);
+        let mut is_removed: Vec<bool> = vec![false; self.nodes.len()];
+        let mut removing: Vec<NodeId> = vec![node];
+        while let Some(removed) = removing.pop() {
+            is_removed[removed] = true;
+            removing.extend(&self.nodes[removed].children);
+        }
+        let new_ids: Vec<NodeId> = is_removed
+            .iter()
+            .scan(0, |next_id, is_removed| {
+                let id: NodeId = *next_id;
+                *next_id += usize::from(!is_removed);
+                Some(id)
+            })
+            .collect();
+        let mut removed = is_removed.iter();
+        self.nodes.retain(|_| !removed.next().unwrap());
+        for node in &mut self.nodes {
+            node.parent = node.parent.map(|parent| new_ids[parent]);
+            for child in &mut node.children {
+                *child = new_ids[*child];
+            }
+        }
+        Some(parent)
+    }
+}
+
+///Returns which kind of NAG it is: move assessments, position assessments or anything else.
+fn nag_group(nag: u8) -> u8 {
+    match nag {
+        0..=9 => 0,
+        10..=135 => 1,
+        _ => 2,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn add(tree: &mut MoveTree, parent: NodeId, notation: &str) -> NodeId {
+        tree.add_move(parent, notation.to_string(), Board::default())
+    }
+
+    #[test]
+    fn delete_removes_the_moves_after_the_node() {
+        let mut tree = MoveTree::default();
+        let e4: NodeId = add(&mut tree, MoveTree::ROOT, "e4");
+        let e5: NodeId = add(&mut tree, e4, "e5");
+        add(&mut tree, e5, "Nf3");
+        add(&mut tree, e4, "c5");
+        let d4: NodeId = add(&mut tree, MoveTree::ROOT, "d4");
+        add(&mut tree, d4, "d5");
+        assert_eq!(tree.nodes.len(), 7);
+
+        assert_eq!(tree.delete(e5), Some(e4));
+        assert_eq!(tree.nodes.len(), 5);
+        let c5: NodeId = tree.children(e4)[0];
+        assert_eq!(tree.notation(c5), "c5");
+        assert_eq!(tree.parent(c5), Some(e4));
+        let d4: NodeId = tree.children(MoveTree::ROOT)[1];
+        assert_eq!(tree.notation(d4), "d4");
+        assert_eq!(tree.notation(tree.line_end(d4)), "d5");
+
+        assert_eq!(tree.delete(e4), Some(MoveTree::ROOT));
+        assert_eq!(tree.nodes.len(), 3);
+        assert_eq!(tree.notation(tree.line_end(MoveTree::ROOT)), "d5");
+        assert_eq!(tree.delete(MoveTree::ROOT), None);
+    }
+}
@@ -1,21 +1,55 @@
 use crate::{
-    board::Board,
-    piece::{Color, Position},
+    board::{Board, Outcome, POCKET_PIECES},
+    clock::{format_time, Clock, TimeControl},
+    editor::PositionEditor,
+    engine::{accepts_draw, Analysis, Analyzer, EngineKind, Line, MAX_DEPTH},
+    net::{Connection, LinkStatus, NetEvent, NetMessage, DEFAULT_PORT, PROTOCOL_VERSION},
+    overlay::BoardOverlay,
+    pgn::{annotate, format_moves, read_pgn, write_pgn, Tags, NAG_GLYPHS},
+    piece::{Color, Piece, PieceType, Position},
+    setup::{GameSetup, PlayerKind, StartingPosition},
+    tree::{Marking, MarkingColor, MoveTree, NodeId},
+    variant::VariantKind,
     Message,
 };
 use iced::{
     advanced::graphics::image::image_rs::ImageFormat,
-    color,
+    border, clipboard, color,
     event::{self, Event},
+    keyboard::{self, key::Named},
+    mouse, time,
     widget::{
-        column, container, mouse_area, row, scrollable,
+        button, canvas, center, checkbox, column, container, mouse_area, opaque, pick_list, row,
+        scrollable,
         scrollable::{Direction, Scrollbar},
-        svg, text, Button, Column, Container, MouseArea, Row, Space, Text,
+        slider, stack, svg, text, text_input, Button, Column, Container, MouseArea, Row, Space,
+        Text,
     },
     window::{self, icon::from_file_data, settings::PlatformSpecific, Icon, Level, Settings},
-    Alignment, Element, Length, Point, Size, Subscription, Theme,
+    Alignment, Border, Element, Length, Point, Shadow, Size, Subscription, Task, Theme, Vector,
 };
 use images::get_image;
+use std::time::{Duration, Instant};
+
+const ANALYSIS_LINE_COUNT: usize = 3;
+const CHAT_LINES_SHOWN: usize = 4;
+const EVALUATION_BAR_WIDTH: u16 = 16;
+const UCI_DEPTH_PER_LEVEL: u32 = 4;
+const LIGHT_SQUARE_COLOR: iced::Color = color!(0x00E3_C16F);
+const DARK_SQUARE_COLOR: iced::Color = color!(0x00B8_8B4A);
+const COORDINATE_SIZE: u16 = 13;
+const SNAP_BACK_DURATION: Duration = Duration::from_millis(150);
+const STATUS_DURATION: Duration = Duration::from_secs(3);
+const HELD_COLOR: iced::Color = color!(0x0080_B040, 0.5);
+const PREMOVE_COLOR: iced::Color = color!(0x0040_70C0, 0.45);
+const LAST_MOVE_COLOR: iced::Color = color!(0x00D8_D040, 0.45);
+const CHECK_COLOR: iced::Color = color!(0x00E0_2020, 0.7);
+const DESTINATION_COLOR: iced::Color = color!(0x0020_2020, 0.35);
+const VARIANT_SQUARE_COLOR: iced::Color = color!(0x00F0_F0F0, 0.3);
+const GREEN_MARKING_COLOR: iced::Color = color!(0x0015_781B, 0.8);
+const RED_MARKING_COLOR: iced::Color = color!(0x0088_2020, 0.8);
+const YELLOW_MARKING_COLOR: iced::Color = color!(0x00E6_8F00, 0.8);
+const BLUE_MARKING_COLOR: iced::Color = color!(0x0000_30CC, 0.8);
 
 #[derive(Default)]
 pub struct UI {
@@ -23,12 +57,72 @@ pub struct UI {
     cursor_active: bool,
     cursor_position: Point,
     board: Board,
-    grabbed_piece_pos: Position,
-    previous_moves: Vec<String>,
+    grabbed_piece_pos: Option<Position>,
+    selected_piece_pos: Option<Position>,
+    premoves: Vec<(Position, Position)>,
+    ///The piece picked from the pocket of the side to move, to be dropped on the board.
+    pocket_piece: Option<PieceType>,
+    ///A pawn move to the last rank waiting for the piece it promotes to to be chosen.
+    promotion_move: Option<(Position, Position)>,
+    ///The square a right-click drag started on, to draw an arrow or circle.
+    marking_start: Option<Position>,
+    modifiers: keyboard::Modifiers,
+    legal_destinations: Vec<Position>,
+    ///Every move played, with the variations tried from earlier positions.
+    tree: MoveTree,
+    ///The move leading to the position on the board.
+    current_node: NodeId,
+    analyzer: Option<Analyzer>,
+    analysis: Analysis,
+    engine_error: Option<String>,
+    status: Option<(String, Instant)>,
+    engine_path: String,
+    engine_search: Option<Analyzer>,
+    clock: Option<Clock>,
+    outcome: Option<Outcome>,
+    game_setup: GameSetup,
+    setup: GameSetup,
+    setup_dialog_open: bool,
+    setup_error: Option<String>,
+    flipped: bool,
+    show_coordinates: bool,
+    snap_back: Option<SnapBack>,
+    ///The position being set up by hand, shown instead of the game while open.
+    editor: Option<PositionEditor>,
+    ///The connection to the other player in a game over the network.
+    network: Option<Connection>,
+    ///The side played on this screen in a game over the network.
+    local_color: Option<Color>,
+    ///The address to join, or the port to host on.
+    network_address: String,
+    ///The messages sent and received in a game over the network, oldest first.
+    chat: Vec<String>,
+    chat_input: String,
+    ///A draw offer or takeback request waiting for an answer.
+    pending_offer: Option<Offer>,
+    ///The sides whose time the other player may set with its next message: the side that just
+    ///moved after a move, or both after a new game.
+    clock_sync: Vec<Color>,
+}
+
+///Something one side asks of the other, which the other can accept or decline.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Offer {
+    ///The given side offers a draw.
+    Draw(Color),
+    ///The given side asks to take back its last move.
+    Takeback(Color),
+}
+
+///A dropped piece on its way back to the square it was picked up from.
+struct SnapBack {
+    piece: Piece,
+    from: Point,
+    started: Instant,
 }
 
 impl UI {
-    pub fn update(&mut self, message: Message) {
+    pub fn update(&mut self, message: Message) -> Task<Message> {
         match message {
             Message::CursorEntered => {
                 self.cursor_active = true;
@@ -40,47 +134,1141 @@ impl UI {
                 self.cursor_position = position;
             }
             Message::LeftButtonPressed => {
-                let box_width = self.window_size.width / 12f32;
-                let box_height = self.window_size.height / 8f32;
-                let position = Position::new(
-                    (self.cursor_position.x / box_width).floor() as u8,
-                    (self.cursor_position.y / box_height).floor() as u8,
-                );
-                if position.x > 7 || position.y > 7 {
-                    return;
+                let Some(position) = self.cursor_square() else {
+                    return Task::none();
+                };
+                if let Some(editor) = &mut self.editor {
+                    if editor.placement[position].piece_type == PieceType::None {
+                        editor.put(position);
+                    } else {
+                        self.grabbed_piece_pos = Some(position);
+                    }
+                    return Task::none();
                 }
-                self.grabbed_piece_pos = position;
+                if let Some(piece_type) = self.pocket_piece.take() {
+                    self.play_human_drop(piece_type, position);
+                    return Task::none();
+                }
+                // With a piece selected, clicking anything but another piece of the same color
+                // moves it there, except for a king castling onto its rook.
+                if let Some(selected_position) = self.selected_piece_pos {
+                    let selected_piece = self.board[selected_position];
+                    let clicked_piece = self.board[position];
+                    if clicked_piece.piece_type == PieceType::None
+                        || clicked_piece.color != selected_piece.color
+                        || self.legal_destinations.contains(&position)
+                    {
+                        self.selected_piece_pos = None;
+                        self.legal_destinations.clear();
+                        self.play_human_move(selected_position, position);
+                        return Task::none();
+                    }
+                }
+                self.grabbed_piece_pos = Some(position);
+                self.show_legal_destinations(position);
             }
             Message::LeftButtonReleased => {
-                let box_width = self.window_size.width / 12f32;
-                let box_height = self.window_size.height / 8f32;
-                let position = Position::new(
-                    (self.cursor_position.x / box_width).floor() as u8,
-                    (self.cursor_position.y / box_height).floor() as u8,
-                );
-                if position.x > 7 || position.y > 7 {
-                    return;
+                // A piece dragged from the pocket is dropped on the square it's released over.
+                if let (Some(piece_type), Some(position)) =
+                    (self.pocket_piece, self.cursor_square())
+                {
+                    self.pocket_piece = None;
+                    self.play_human_drop(piece_type, position);
+                    return Task::none();
+                }
+                let Some(old_position) = self.grabbed_piece_pos.take() else {
+                    return Task::none();
+                };
+                let new_position: Option<Position> = self.cursor_square();
+                if let Some(editor) = &mut self.editor {
+                    match new_position {
+                        Some(new_position) if new_position == old_position => {
+                            editor.put(old_position);
+                        }
+                        Some(new_position) => editor.move_piece(old_position, new_position),
+                        None => editor.remove(old_position),
+                    }
+                    return Task::none();
+                }
+                // Releasing on the square the piece was picked up from is a click, which selects
+                // the piece, or deselects it if it was already selected.
+                if self.cursor_square() == Some(old_position) {
+                    if self.selected_piece_pos.take() == Some(old_position)
+                        || self.board[old_position].piece_type == PieceType::None
+                    {
+                        self.legal_destinations.clear();
+                    } else {
+                        self.selected_piece_pos = Some(old_position);
+                    }
+                    return Task::none();
                 }
-                let new_position = position;
-                match self.board.move_piece(self.grabbed_piece_pos, new_position) {
-                    Some(notation) => self.previous_moves.push(notation),
-                    None => println!("Invalid Move"),
+                self.selected_piece_pos = None;
+                self.legal_destinations.clear();
+                let is_played = match self.cursor_square() {
+                    Some(new_position) => self.play_human_move(old_position, new_position),
+                    None => false,
+                };
+                if !is_played {
+                    self.start_snap_back(old_position);
                 }
             }
-            Message::RestartButtonPressed => {
-                self.board = Board::default();
-                self.previous_moves.clear();
+            Message::RightButtonPressed => {
+                let cursor_square: Option<Position> = self.cursor_square();
+                if let Some(editor) = &mut self.editor {
+                    if let Some(position) = cursor_square {
+                        editor.remove(position);
+                    }
+                    return Task::none();
+                }
+                self.premoves.clear();
+                self.selected_piece_pos = None;
+                self.pocket_piece = None;
+                self.legal_destinations.clear();
+                self.marking_start = self.cursor_square();
+            }
+            Message::RightButtonReleased => {
+                let (Some(start), Some(end)) = (self.marking_start.take(), self.cursor_square())
+                else {
+                    return Task::none();
+                };
+                let color: MarkingColor = self.marking_color();
+                let marking = if start == end {
+                    Marking::Circle(color, start)
+                } else {
+                    Marking::Arrow(color, start, end)
+                };
+                self.tree.toggle_marking(self.current_node, marking);
+            }
+            Message::PocketPiecePressed(piece_type) => {
+                self.selected_piece_pos = None;
+                if self.pocket_piece == Some(piece_type) || !self.is_human_turn() {
+                    self.pocket_piece = None;
+                    self.legal_destinations.clear();
+                    return Task::none();
+                }
+                self.pocket_piece = Some(piece_type);
+                self.legal_destinations = self.board.legal_drop_squares(piece_type);
+            }
+            Message::PromotionSelected(promotion) => {
+                let (Some((old_position, new_position)), Some(promotion)) =
+                    (self.promotion_move.take(), promotion)
+                else {
+                    return Task::none();
+                };
+                match self
+                    .board
+                    .promote_piece(old_position, new_position, promotion)
+                {
+                    Ok(notation) => self.record_move(notation),
+                    Err(error) => self.show_status(error.to_string()),
+                }
+            }
+            Message::StatusTick(now) => {
+                if self.status.as_ref().is_some_and(|(_, shown)| {
+                    now.saturating_duration_since(*shown) >= STATUS_DURATION
+                }) {
+                    self.status = None;
+                }
+            }
+            Message::AnimationTick(now) => {
+                if self.snap_back.as_ref().is_some_and(|snap_back| {
+                    now.saturating_duration_since(snap_back.started) >= SNAP_BACK_DURATION
+                }) {
+                    self.snap_back = None;
+                }
+            }
+            Message::NewGameButtonPressed => {
+                self.setup = self.game_setup.clone();
+                self.setup_error = None;
+                self.setup_dialog_open = true;
+            }
+            Message::SetupChanged(setup) => {
+                self.setup = setup;
+            }
+            Message::CancelSetupButtonPressed => {
+                self.setup_dialog_open = false;
+            }
+            Message::StartGameButtonPressed => match self.setup.board() {
+                Ok(board) => {
+                    self.game_setup = self.setup.clone();
+                    self.setup_dialog_open = false;
+                    self.start_game(board);
+                    self.send_new_game();
+                }
+                Err(error) => self.setup_error = Some(error.to_string()),
+            },
+            Message::ResignButtonPressed => {
+                let color: Color = self.acting_color();
+                self.send(&NetMessage::Resign);
+                self.end_game(Outcome::Resignation(!color));
+            }
+            Message::OfferDrawButtonPressed => {
+                let color: Color = self.acting_color();
+                match self.game_setup.player(!color) {
+                    PlayerKind::Engine => {
+                        let board: &Board = self.tree.board(self.tree.line_end(MoveTree::ROOT));
+                        if accepts_draw(board, !color) {
+                            self.end_game(Outcome::DrawAgreed);
+                        } else {
+                            self.show_status("The engine declines the draw".to_string());
+                        }
+                    }
+                    PlayerKind::Remote => {
+                        self.send(&NetMessage::DrawOffer);
+                        self.pending_offer = Some(Offer::Draw(color));
+                    }
+                    PlayerKind::Human => self.pending_offer = Some(Offer::Draw(color)),
+                }
+            }
+            Message::RequestTakebackButtonPressed => {
+                let color: Color = self.takeback_color();
+                match self.game_setup.player(!color) {
+                    // The engine always lets a move be taken back.
+                    PlayerKind::Engine => self.take_back(color),
+                    PlayerKind::Remote => {
+                        self.send(&NetMessage::TakebackRequest);
+                        self.pending_offer = Some(Offer::Takeback(color));
+                    }
+                    PlayerKind::Human => self.pending_offer = Some(Offer::Takeback(color)),
+                }
+            }
+            Message::AcceptOfferButtonPressed => match self.pending_offer.take() {
+                Some(Offer::Draw(_)) => {
+                    self.send(&NetMessage::DrawReply(true));
+                    self.end_game(Outcome::DrawAgreed);
+                }
+                Some(Offer::Takeback(color)) => {
+                    self.send(&NetMessage::TakebackReply(true));
+                    self.take_back(color);
+                    self.send_clock();
+                }
+                None => (),
+            },
+            Message::DeclineOfferButtonPressed => match self.pending_offer.take() {
+                Some(Offer::Draw(_)) => {
+                    self.send(&NetMessage::DrawReply(false));
+                }
+                Some(Offer::Takeback(_)) => {
+                    self.send(&NetMessage::TakebackReply(false));
+                }
+                None => (),
+            },
+            Message::CopyPgnButtonPressed => {
+                return clipboard::write(self.make_pgn());
+            }
+            Message::EditPositionButtonPressed => {
+                self.editor = Some(PositionEditor::new(&self.board));
+                self.grabbed_piece_pos = None;
+                self.selected_piece_pos = None;
+                self.pocket_piece = None;
+                self.legal_destinations.clear();
+                self.premoves.clear();
+                self.marking_start = None;
+            }
+            Message::EditorBrushSelected(brush) => {
+                if let Some(editor) = &mut self.editor {
+                    editor.brush = brush;
+                }
+            }
+            Message::EditorTurnSelected(turn) => {
+                if let Some(editor) = &mut self.editor {
+                    editor.turn = turn;
+                }
+            }
+            Message::EditorCastlingToggled(index, is_allowed) => {
+                if let Some(editor) = &mut self.editor {
+                    editor.castling[index] = is_allowed;
+                }
+            }
+            Message::EditorEnPassantChanged(square) => {
+                if let Some(editor) = &mut self.editor {
+                    editor.en_passant = square;
+                }
+            }
+            Message::ClearBoardButtonPressed => {
+                if let Some(editor) = &mut self.editor {
+                    editor.clear();
+                }
             }
-            Message::WindowEventOccurred(event) => {
-                if let Event::Window(window::Event::Resized(size)) = event {
+            Message::StartingPositionButtonPressed => {
+                if let Some(editor) = &mut self.editor {
+                    editor.reset();
+                }
+            }
+            Message::CopyFenButtonPressed => {
+                if let Some(editor) = &self.editor {
+                    return clipboard::write(editor.fen());
+                }
+            }
+            Message::CancelEditButtonPressed => {
+                self.editor = None;
+                self.grabbed_piece_pos = None;
+            }
+            Message::PlayPositionButtonPressed => {
+                let Some(editor) = &self.editor else {
+                    return Task::none();
+                };
+                if let Ok(mut board) = editor.board() {
+                    board.variant = self.game_setup.variant;
+                    self.game_setup.starting_position = StartingPosition::Fen;
+                    self.game_setup.fen = editor.fen();
+                    self.editor = None;
+                    self.grabbed_piece_pos = None;
+                    self.start_game(board);
+                    self.send_new_game();
+                }
+            }
+            Message::ClockTick(now) => {
+                let Some(clock) = &mut self.clock else {
+                    return Task::none();
+                };
+                if let Some(flagged_color) = clock.flagged_side(now) {
+                    // The flag falls in the game, whichever position is being browsed.
+                    let end: &Board = self.tree.board(self.tree.line_end(MoveTree::ROOT));
+                    self.end_game(end.timeout_outcome(flagged_color));
+                }
+            }
+            Message::AnalysisButtonPressed => {
+                if self.analyzer.is_some() {
+                    self.analyzer = None;
+                    self.analysis = Analysis::default();
+                } else {
+                    self.start_analysis();
+                }
+            }
+            Message::EnginePathChanged(path) => {
+                self.engine_path = path;
+            }
+            Message::NetworkAddressChanged(address) => {
+                self.network_address = address;
+            }
+            Message::HostButtonPressed => {
+                let address: &str = self.network_address.trim();
+                let port: u16 = if address.is_empty() {
+                    DEFAULT_PORT
+                } else if let Ok(port) = address.parse() {
+                    port
+                } else {
+                    self.show_status("Enter a port number to host on".to_string());
+                    return Task::none();
+                };
+                match Connection::host(port) {
+                    Ok(connection) => {
+                        let color = if self.flipped {
+                            Color::Black
+                        } else {
+                            Color::White
+                        };
+                        self.network = Some(connection);
+                        self.local_color = Some(color);
+                        self.chat.clear();
+                        self.game_setup.set_player(color, PlayerKind::Human);
+                        self.game_setup.set_player(!color, PlayerKind::Remote);
+                        self.start_engine_move();
+                    }
+                    Err(error) => self.show_status(format!("Couldn't host a game: {error}")),
+                }
+            }
+            Message::JoinButtonPressed => {
+                let mut address: String = self.network_address.trim().to_string();
+                if !address.contains(':') {
+                    address = format!("{address}:{DEFAULT_PORT}");
+                }
+                match Connection::join(&address) {
+                    Ok(connection) => {
+                        self.network = Some(connection);
+                        self.chat.clear();
+                    }
+                    Err(error) => self.show_status(format!("Couldn't join {address}: {error}")),
+                }
+            }
+            Message::DisconnectButtonPressed => self.disconnect(),
+            Message::ChatInputChanged(chat_input) => {
+                self.chat_input = chat_input;
+            }
+            Message::SendChatButtonPressed => {
+                let text: String = self.chat_input.trim().to_string();
+                if text.is_empty() {
+                    return Task::none();
+                }
+                if self.send(&NetMessage::Chat(text.clone())) {
+                    self.chat.push(format!("You: {text}"));
+                    self.chat_input.clear();
+                } else {
+                    self.show_status("The message couldn't be sent".to_string());
+                }
+            }
+            Message::NetworkTick => {
+                let Some(network) = &self.network else {
+                    return Task::none();
+                };
+                for event in network.events() {
+                    self.handle_network_event(event);
+                }
+            }
+            Message::EngineTick => {
+                if let Some(analyzer) = &self.analyzer {
+                    self.analysis = analyzer.analysis();
+                }
+                let Some(search) = &self.engine_search else {
+                    return Task::none();
+                };
+                let analysis: Analysis = search.analysis();
+                if !analysis.finished {
+                    return Task::none();
+                }
+                self.engine_search = None;
+                let Some(first_move) = analysis.lines.first().and_then(|line| line.moves.first())
+                else {
+                    self.engine_error =
+                        Some("The engine stopped without choosing a move".to_string());
+                    return Task::none();
+                };
+                match self.board.play_san(first_move) {
+                    Some(notation) => self.record_move(notation),
+                    None => {
+                        self.engine_error = Some(format!(
+                            "The engine chose a move that can't be played: {first_move}"
+                        ));
+                    }
+                }
+            }
+            Message::CoordinatesToggled(show_coordinates) => {
+                self.show_coordinates = show_coordinates;
+            }
+            Message::FlipBoardButtonPressed => {
+                self.flipped = !self.flipped;
+            }
+            Message::MoveSelected(node) => self.go_to_node(node),
+            Message::CommentChanged(comment) => self.tree.set_comment(self.current_node, comment),
+            Message::NagToggled(nag) => self.tree.toggle_nag(self.current_node, nag),
+            Message::PromoteVariationButtonPressed => {
+                self.tree.promote(self.current_node);
+                self.update_outcome();
+            }
+            Message::DeleteMoveButtonPressed => {
+                if let Some(parent) = self.tree.delete(self.current_node) {
+                    self.update_outcome();
+                    self.go_to_node(parent);
+                }
+            }
+            Message::PastePgnButtonPressed => {
+                return clipboard::read().map(Message::PgnPasted);
+            }
+            Message::PgnPasted(pgn) => match read_pgn(pgn.as_deref().unwrap_or_default()) {
+                Ok((tags, tree)) => {
+                    self.load_game(&tags, tree);
+                    self.send_new_game();
+                }
+                Err(error) => self.show_status(format!("Couldn't paste PGN: {error}")),
+            },
+            Message::WindowEventOccurred(event) => match event {
+                Event::Window(window::Event::Resized(size)) => {
                     self.window_size = size;
                 }
+                Event::Mouse(mouse::Event::ButtonReleased(mouse::Button::Right)) => {
+                    self.marking_start = None;
+                }
+                Event::Keyboard(keyboard::Event::ModifiersChanged(modifiers)) => {
+                    self.modifiers = modifiers;
+                }
+                Event::Mouse(mouse::Event::ButtonReleased(mouse::Button::Left)) => {
+                    // The button was released off the board, so the held piece goes back, or is
+                    // taken off while editing.
+                    if let Some(old_position) = self.grabbed_piece_pos.take() {
+                        if let Some(editor) = &mut self.editor {
+                            editor.remove(old_position);
+                            return Task::none();
+                        }
+                        self.legal_destinations.clear();
+                        self.start_snap_back(old_position);
+                    }
+                }
+                Event::Keyboard(keyboard::Event::KeyPressed {
+                    key: keyboard::Key::Character(key),
+                    ..
+                }) if key.as_str() == "f" => {
+                    self.flipped = !self.flipped;
+                }
+                Event::Keyboard(keyboard::Event::KeyPressed {
+                    key: keyboard::Key::Named(key),
+                    ..
+                }) if self.editor.is_none() => match key {
+                    Named::ArrowLeft => {
+                        if let Some(parent) = self.tree.parent(self.current_node) {
+                            self.go_to_node(parent);
+                        }
+                    }
+                    Named::ArrowRight => {
+                        if let Some(child) = self.tree.children(self.current_node).first() {
+                            self.go_to_node(*child);
+                        }
+                    }
+                    Named::Home => self.go_to_node(MoveTree::ROOT),
+                    Named::End => self.go_to_node(self.tree.line_end(self.current_node)),
+                    _ => (),
+                },
+                _ => (),
+            },
+        }
+        Task::none()
+    }
+    ///Starts a new game from the given board with the chosen setup.
+    fn start_game(&mut self, board: Board) {
+        // A game over the network keeps being played against the other player.
+        if let Some(color) = self.local_color {
+            self.game_setup.set_player(color, PlayerKind::Human);
+            self.game_setup.set_player(!color, PlayerKind::Remote);
+        }
+        match (self.game_setup.white_player, self.game_setup.black_player) {
+            (PlayerKind::Human, PlayerKind::Engine | PlayerKind::Remote) => self.flipped = false,
+            (PlayerKind::Engine | PlayerKind::Remote, PlayerKind::Human) => self.flipped = true,
+            _ => (),
+        }
+        self.tree = MoveTree::new(board.clone());
+        self.current_node = MoveTree::ROOT;
+        self.board = board;
+        self.selected_piece_pos = None;
+        self.pocket_piece = None;
+        self.promotion_move = None;
+        self.legal_destinations.clear();
+        self.premoves.clear();
+        self.pending_offer = None;
+        self.outcome = self.board.outcome();
+        self.clock = self.new_clock();
+        self.restart_analysis();
+        self.start_engine_move();
+    }
+    ///Adds a move that was just played to the move list. A move continuing the game at the end
+    ///of its mainline also updates the clock and game status, while any other move only adds to
+    ///a variation.
+    fn record_move(&mut self, notation: String) {
+        let now = Instant::now();
+        self.selected_piece_pos = None;
+        self.pocket_piece = None;
+        self.legal_destinations.clear();
+        let is_game_move: bool = self.outcome.is_none() && self.is_at_game_end();
+        self.current_node = self
+            .tree
+            .add_move(self.current_node, notation, self.board.clone());
+        self.restart_analysis();
+        if !is_game_move {
+            return;
+        }
+        self.update_outcome();
+        if let Some(clock) = &mut self.clock {
+            clock.press(!self.board.turn, now);
+            if self.outcome.is_some() {
+                clock.stop(now);
+            }
+        }
+        if self.outcome.is_some() {
+            self.premoves.clear();
+        }
+        // Moving declines a draw offered by the opponent, and changes the position a takeback
+        // was asked for in.
+        let mover: Color = !self.board.turn;
+        self.pending_offer = self
+            .pending_offer
+            .filter(|offer| *offer == Offer::Draw(mover) && self.outcome.is_none());
+        if self.game_setup.player(mover) != PlayerKind::Remote {
+            if let Some(uci) = self.board.last_move_uci() {
+                self.send(&NetMessage::Move(uci));
+                self.send_clock();
+            }
+        }
+        self.start_engine_move();
+        if self.is_human_turn() {
+            self.play_premove();
+        }
+    }
+    ///Plays a move made on the board by a human, or queues it as a premove while waiting for
+    ///the opponent. Returns whether the move was played or queued.
+    fn play_human_move(&mut self, old_position: Position, new_position: Position) -> bool {
+        if self.is_human_turn() && self.board.is_promotion(old_position, new_position) {
+            self.promotion_move = Some((old_position, new_position));
+            true
+        } else if self.is_human_turn() {
+            match self.board.move_piece(old_position, new_position) {
+                Ok(notation) => {
+                    self.record_move(notation);
+                    true
+                }
+                Err(error) => {
+                    self.show_status(error.to_string());
+                    false
+                }
+            }
+        } else if self.can_premove(old_position, new_position) {
+            self.premoves.push((old_position, new_position));
+            true
+        } else {
+            false
+        }
+    }
+    ///Drops a piece from the pocket of the side to move, if it's a human's turn.
+    fn play_human_drop(&mut self, piece_type: PieceType, position: Position) {
+        self.legal_destinations.clear();
+        if !self.is_human_turn() {
+            return;
+        }
+        match self.board.drop_piece(piece_type, position) {
+            Ok(notation) => self.record_move(notation),
+            Err(error) => self.show_status(error.to_string()),
+        }
+    }
+    ///Checks if a human can queue a move from one square to another while the opponent is
+    ///thinking. The piece can be on the board or on the end square of an earlier premove.
+    fn can_premove(&self, old_position: Position, new_position: Position) -> bool {
+        let piece = self.board[old_position];
+        self.outcome.is_none()
+            && self.is_at_game_end()
+            && self.game_setup.player(self.board.turn) != PlayerKind::Human
+            && self.game_setup.player(!self.board.turn) == PlayerKind::Human
+            && old_position != new_position
+            && ((piece.piece_type != PieceType::None && piece.color != self.board.turn)
+                || self.premoves.iter().any(|(_, end)| *end == old_position))
+    }
+    ///Plays the first queued premove, cancelling the rest if it turns out to be illegal.
+    fn play_premove(&mut self) {
+        if self.premoves.is_empty() {
+            return;
+        }
+        let (old_position, new_position) = self.premoves.remove(0);
+        match self.board.move_piece(old_position, new_position) {
+            Ok(notation) => self.record_move(notation),
+            Err(error) => {
+                self.premoves.clear();
+                self.show_status(format!("Premove cancelled: {error}"));
+            }
+        }
+    }
+    ///Returns a clock for the chosen time control, or `None` if the game is untimed.
+    fn new_clock(&self) -> Option<Clock> {
+        if self.game_setup.time_control.is_untimed() {
+            return None;
+        }
+        Some(Clock::new(self.game_setup.time_control.clone()))
+    }
+    ///Returns the game so far as PGN, with the result of the game rather than of the position
+    ///being browsed.
+    fn make_pgn(&self) -> String {
+        let mut tags = self.game_setup.tags(&self.engine_name());
+        if let Some(outcome) = self.outcome {
+            tags.set("Result", outcome.result());
+        }
+        // A variant game may have started from a random position, so it's always written out.
+        if self.game_setup.variant != VariantKind::Standard {
+            tags.set("SetUp", "1");
+            tags.set("FEN", self.tree.board(MoveTree::ROOT).to_fen());
+        }
+        write_pgn(&tags, &self.tree)
+    }
+    ///Starts a game read from PGN, showing the position at the end of its mainline.
+    fn load_game(&mut self, tags: &Tags, tree: MoveTree) {
+        let name = |tag: &str| {
+            tags.get(tag)
+                .filter(|name| *name != "?")
+                .unwrap_or_default()
+                .to_string()
+        };
+        self.game_setup = GameSetup {
+            white_name: name("White"),
+            black_name: name("Black"),
+            starting_position: if tags.get("FEN").is_some() {
+                StartingPosition::Fen
+            } else {
+                StartingPosition::Standard
+            },
+            fen: tags.get("FEN").unwrap_or_default().to_string(),
+            variant: VariantKind::from_pgn_name(tags.get("Variant").unwrap_or_default()),
+            ..GameSetup::default()
+        };
+        self.start_game(tree.board(MoveTree::ROOT).clone());
+        self.tree = tree;
+        self.update_outcome();
+        self.go_to_node(self.tree.line_end(MoveTree::ROOT));
+    }
+    ///Shows the position after the given move. Moves played from there start a variation if
+    ///the position already has a different continuation.
+    fn go_to_node(&mut self, node: NodeId) {
+        self.current_node = node;
+        self.board = self.tree.board(node).clone();
+        self.grabbed_piece_pos = None;
+        self.selected_piece_pos = None;
+        self.pocket_piece = None;
+        self.promotion_move = None;
+        self.legal_destinations.clear();
+        self.premoves.clear();
+        self.restart_analysis();
+        self.start_engine_move();
+    }
+    ///Checks if the board shows the end of the game's mainline, where moves continue the game
+    ///instead of adding to a variation.
+    fn is_at_game_end(&self) -> bool {
+        self.current_node == self.tree.line_end(MoveTree::ROOT)
+    }
+    ///Sets the game's outcome from the position at the end of its mainline, unless the game was
+    ///ended off the board, which holds whatever line is played out.
+    fn update_outcome(&mut self) {
+        if !matches!(
+            self.outcome,
+            Some(
+                Outcome::Timeout(_)
+                    | Outcome::TimeoutVsInsufficientMaterial
+                    | Outcome::Resignation(_)
+                    | Outcome::DrawAgreed
+            )
+        ) {
+            self.outcome = self
+                .tree
+                .board(self.tree.line_end(MoveTree::ROOT))
+                .outcome();
+        }
+    }
+    ///Sends a message to the other player in a game over the network, returning whether it
+    ///could be sent.
+    fn send(&self, message: &NetMessage) -> bool {
+        self.network
+            .as_ref()
+            .is_some_and(|network| network.send(message))
+    }
+    ///Sends the game to the other player, with the moves of its mainline and the clock.
+    fn send_new_game(&self) {
+        let Some(color) = self.local_color else {
+            return;
+        };
+        let mut moves: Vec<String> = Vec::new();
+        let mut node: NodeId = MoveTree::ROOT;
+        while let Some(child) = self.tree.children(node).first() {
+            node = *child;
+            moves.extend(self.tree.board(node).last_move_uci());
+        }
+        self.send(&NetMessage::NewGame {
+            color: !color,
+            variant: self.game_setup.variant,
+            fen: self.tree.board(MoveTree::ROOT).to_fen(),
+            time_control: self.game_setup.time_control.clone(),
+            moves,
+        });
+        self.send_clock();
+    }
+    ///Sends both sides' time left to the other player, so their clock agrees.
+    fn send_clock(&self) {
+        if let Some(clock) = &self.clock {
+            let now = Instant::now();
+            self.send(&NetMessage::Clock(
+                clock.remaining(Color::White, now),
+                clock.remaining(Color::Black, now),
+            ));
+        }
+    }
+    ///Acts on something that happened on the connection to the other player.
+    fn handle_network_event(&mut self, event: NetEvent) {
+        match event {
+            NetEvent::Connected => {
+                self.send(&NetMessage::Hello(PROTOCOL_VERSION));
+                // The host sends the game again every time the other player connects, so a
+                // player who lost the connection picks the game back up.
+                if self
+                    .network
+                    .as_ref()
+                    .is_some_and(|network| network.port().is_some())
+                {
+                    self.send_new_game();
+                }
+                self.show_status("Connected to the other player".to_string());
+            }
+            NetEvent::Disconnected => {
+                self.show_status("The other player lost the connection".to_string());
+            }
+            NetEvent::Error(error) => {
+                self.show_status(format!("Couldn't read the other player's message: {error}"));
+            }
+            NetEvent::VersionMismatch(version) => {
+                self.disconnect();
+                self.show_status(format!(
+                    "The other player's version of the game can't be played against (protocol \
+                     {version}, not {PROTOCOL_VERSION})"
+                ));
+            }
+            NetEvent::Received(message) => self.handle_net_message(message),
+        }
+    }
+    ///Acts on a message from the other player in a game over the network.
+    fn handle_net_message(&mut self, message: NetMessage) {
+        let clock_sync: Vec<Color> = std::mem::take(&mut self.clock_sync);
+        match message {
+            // Another protocol version is caught by the connection before it gets here.
+            NetMessage::Hello(_) => (),
+            NetMessage::NewGame {
+                color,
+                variant,
+                fen,
+                time_control,
+                moves,
+            } => {
+                let board = match Board::from_variant_fen(&fen, variant) {
+                    Ok(board) => board,
+                    Err(error) => {
+                        self.show_status(format!(
+                            "The other player's game can't be set up: {error}"
+                        ));
+                        return;
+                    }
+                };
+                self.local_color = Some(color);
+                self.game_setup.variant = variant;
+                self.game_setup.starting_position = StartingPosition::Fen;
+                self.game_setup.fen = fen;
+                self.game_setup.time_control = time_control;
+                self.setup_dialog_open = false;
+                self.editor = None;
+                self.start_game(board);
+                // The moves already played are replayed without sending them back.
+                let now = Instant::now();
+                for uci in moves {
+                    let Some(notation) = self.board.move_uci(&uci) else {
+                        self.show_status(format!("The other player sent an illegal move: {uci}"));
+                        break;
+                    };
+                    self.current_node =
+                        self.tree
+                            .add_move(self.current_node, notation, self.board.clone());
+                    if let Some(clock) = &mut self.clock {
+                        clock.press(!self.board.turn, now);
+                    }
+                }
+                self.outcome = self.board.outcome();
+                if let (Some(clock), Some(_)) = (&mut self.clock, self.outcome) {
+                    clock.stop(now);
+                }
+                // The host's clock follows, so a game picked back up keeps its times.
+                self.clock_sync = vec![Color::White, Color::Black];
+                self.restart_analysis();
+            }
+            NetMessage::Move(uci) => {
+                let end: NodeId = self.tree.line_end(MoveTree::ROOT);
+                if self.current_node != end {
+                    self.go_to_node(end);
+                }
+                if self.outcome.is_some()
+                    || self.game_setup.player(self.board.turn) != PlayerKind::Remote
+                {
+                    self.show_status(format!("The other player sent a move out of turn: {uci}"));
+                    return;
+                }
+                let mut board: Board = self.board.clone();
+                match board.move_uci(&uci) {
+                    Some(notation) => {
+                        self.board = board;
+                        self.record_move(notation);
+                        self.clock_sync = vec![!self.board.turn];
+                    }
+                    None => {
+                        self.show_status(format!("The other player sent an illegal move: {uci}"));
+                    }
+                }
+            }
+            NetMessage::Clock(white, black) => {
+                if let Some(clock) = &mut self.clock {
+                    let now = Instant::now();
+                    for color in clock_sync {
+                        clock.sync(
+                            color,
+                            if color == Color::White { white } else { black },
+                            now,
+                        );
+                    }
+                }
+            }
+            NetMessage::Chat(text) => self.chat.push(format!("Opponent: {text}")),
+            NetMessage::Resign => {
+                if let (Some(color), None) = (self.local_color, self.outcome) {
+                    self.end_game(Outcome::Resignation(color));
+                }
+            }
+            NetMessage::DrawOffer => {
+                if let (Some(color), None) = (self.local_color, self.outcome) {
+                    self.pending_offer = Some(Offer::Draw(!color));
+                }
+            }
+            NetMessage::TakebackRequest => {
+                if let (Some(color), None) = (self.local_color, self.outcome) {
+                    self.pending_offer = Some(Offer::Takeback(!color));
+                }
+            }
+            NetMessage::DrawReply(is_accepted) => {
+                let Some(Offer::Draw(_)) = self.pending_offer.take() else {
+                    return;
+                };
+                if is_accepted {
+                    self.end_game(Outcome::DrawAgreed);
+                } else {
+                    self.show_status("The other player declined the draw".to_string());
+                }
+            }
+            NetMessage::TakebackReply(is_accepted) => {
+                let Some(Offer::Takeback(color)) = self.pending_offer.take() else {
+                    return;
+                };
+                if is_accepted {
+                    self.take_back(color);
+                } else {
+                    self.show_status("The other player declined the takeback".to_string());
+                }
+            }
+        }
+    }
+    ///Ends the game with an outcome reached off the board, such as a resignation, stopping the
+    ///clock and the engine.
+    fn end_game(&mut self, outcome: Outcome) {
+        self.outcome = Some(outcome);
+        if let Some(clock) = &mut self.clock {
+            clock.stop(Instant::now());
+        }
+        self.engine_search = None;
+        self.premoves.clear();
+        self.pending_offer = None;
+    }
+    ///Returns the side the buttons in the side panel act for, which is the side played on this
+    ///screen over the network, the human against the engine, or else the side to move in the
+    ///game.
+    fn acting_color(&self) -> Color {
+        if let Some(color) = self.local_color {
+            return color;
+        }
+        let turn: Color = self.tree.board(self.tree.line_end(MoveTree::ROOT)).turn;
+        if self.game_setup.player(turn) == PlayerKind::Human {
+            turn
+        } else {
+            !turn
+        }
+    }
+    ///Returns the side asking to take back a move, which in a game between two humans on this
+    ///screen is the side that moved last.
+    fn takeback_color(&self) -> Color {
+        let color: Color = self.acting_color();
+        if self.local_color.is_none()
+            && self.game_setup.player(color) == PlayerKind::Human
+            && self.game_setup.player(!color) == PlayerKind::Human
+        {
+            return !color;
+        }
+        color
+    }
+    ///Returns the position before the given side's last move in the game, if it has moved, with
+    ///the number of moves played since.
+    fn takeback_node(&self, color: Color) -> Option<(NodeId, usize)> {
+        let mut node: NodeId = self.tree.line_end(MoveTree::ROOT);
+        let mut moves: usize = 0;
+        loop {
+            node = self.tree.parent(node)?;
+            moves += 1;
+            if self.tree.board(node).turn == color {
+                return Some((node, moves));
+            }
+        }
+    }
+    ///Takes back the given side's last move, with the opponent's reply if there was one, and
+    ///gives the side its turn again.
+    fn take_back(&mut self, color: Color) {
+        let Some((node, moves)) = self.takeback_node(color) else {
+            return;
+        };
+        // Variations tried from the position go as well, so none of them takes the game's place.
+        while let Some(child) = self.tree.children(node).first().copied() {
+            self.tree.delete(child);
+        }
+        self.update_outcome();
+        self.go_to_node(node);
+        if let Some(clock) = &mut self.clock {
+            clock.take_back(moves, color, Instant::now());
+        }
+    }
+    ///Closes the connection to the other player, leaving both sides to be played on this
+    ///screen.
+    fn disconnect(&mut self) {
+        self.network = None;
+        self.local_color = None;
+        for color in [Color::White, Color::Black] {
+            if self.game_setup.player(color) == PlayerKind::Remote {
+                self.game_setup.set_player(color, PlayerKind::Human);
+            }
+        }
+    }
+    ///Returns the color to draw arrows and circles in, picked by holding Shift for red, Alt for
+    ///blue or Ctrl for yellow.
+    fn marking_color(&self) -> MarkingColor {
+        if self.modifiers.shift() {
+            MarkingColor::Red
+        } else if self.modifiers.alt() {
+            MarkingColor::Blue
+        } else if self.modifiers.control() {
+            MarkingColor::Yellow
+        } else {
+            MarkingColor::Green
+        }
+    }
+    ///Shows a message in the side panel for a few seconds.
+    fn show_status(&mut self, status: String) {
+        self.status = Some((status, Instant::now()));
+    }
+    ///Checks if a human is to move in a game that isn't over, or can try a move for either side
+    ///in a variation while browsing earlier moves.
+    ///
+    ///In a game over the network, moves are only played while connected and at the end of the
+    ///mainline, so both boards stay the same.
+    fn is_human_turn(&self) -> bool {
+        if !self.is_at_game_end() {
+            return self.network.is_none() && self.board.outcome().is_none();
+        }
+        self.outcome.is_none()
+            && self.game_setup.player(self.board.turn) == PlayerKind::Human
+            && self
+                .network
+                .as_ref()
+                .is_none_or(|network| network.status() == LinkStatus::Connected)
+    }
+    ///Shows where the piece on a square can move to, if it's a human's turn.
+    fn show_legal_destinations(&mut self, position: Position) {
+        self.legal_destinations.clear();
+        if self.is_human_turn() {
+            self.legal_destinations = self.board.legal_destinations(position);
+        }
+    }
+    ///Starts animating a dropped piece back to the square it was picked up from.
+    fn start_snap_back(&mut self, old_position: Position) {
+        let piece = self.board[old_position];
+        if piece.piece_type == PieceType::None {
+            return;
+        }
+        self.snap_back = Some(SnapBack {
+            piece,
+            from: self.cursor_position,
+            started: Instant::now(),
+        });
+    }
+    ///Checks if the piece on a square is being dragged or animated instead of drawn in place.
+    fn is_lifted(&self, position: Position) -> bool {
+        self.grabbed_piece_pos == Some(position)
+            || self
+                .snap_back
+                .as_ref()
+                .is_some_and(|snap_back| snap_back.piece.position == position)
+    }
+    ///Returns the board drawn on the screen, which is the one being edited while the editor is
+    ///open.
+    fn shown_board(&self) -> &Board {
+        match &self.editor {
+            Some(editor) => &editor.placement,
+            None => &self.board,
+        }
+    }
+    ///Returns the center of a square on the screen.
+    fn square_center(&self, position: Position) -> Point {
+        let square_size = self.square_size();
+        let screen_position = self.orient(position);
+        Point::new(
+            (f32::from(screen_position.x) + 0.5) * square_size.width,
+            (f32::from(screen_position.y) + 0.5) * square_size.height,
+        )
+    }
+    ///Returns the size of one square on the screen.
+    fn square_size(&self) -> Size {
+        let mut board_width = self.window_size.width;
+        if self.analyzer.is_some() {
+            board_width -= f32::from(EVALUATION_BAR_WIDTH);
+        }
+        Size::new(board_width / 12f32, self.window_size.height / 8f32)
+    }
+    ///Returns the square under the cursor, if it is on the board.
+    fn cursor_square(&self) -> Option<Position> {
+        let square_size = self.square_size();
+        let position = Position::new(
+            (self.cursor_position.x / square_size.width).floor() as u8,
+            (self.cursor_position.y / square_size.height).floor() as u8,
+        );
+        if position.x > 7 || position.y > 7 {
+            return None;
+        }
+        Some(self.orient(position))
+    }
+    ///Converts between a square on the screen and a square on the board, depending on which
+    ///side is at the bottom.
+    fn orient(&self, position: Position) -> Position {
+        if self.flipped {
+            return Position::new(7 - position.x, 7 - position.y);
+        }
+        position
+    }
+    ///Returns the engine chosen in the side panel.
+    fn engine_kind(&self) -> EngineKind {
+        if self.engine_path.trim().is_empty() {
+            EngineKind::BuiltIn
+        } else {
+            EngineKind::Uci(self.engine_path.trim().to_string())
+        }
+    }
+    ///Returns the name of the chosen engine for the PGN tags.
+    fn engine_name(&self) -> String {
+        match self.engine_kind() {
+            EngineKind::BuiltIn => "Built-in Engine".to_string(),
+            EngineKind::Uci(path) => std::path::Path::new(&path)
+                .file_stem()
+                .map_or(path.clone(), |name| name.to_string_lossy().to_string()),
+        }
+    }
+    ///Starts analysing the current position with the chosen engine.
+    fn start_analysis(&mut self) {
+        self.analysis = Analysis::default();
+        self.analyzer = None;
+        match Analyzer::start(&self.engine_kind(), &self.board, ANALYSIS_LINE_COUNT, None) {
+            Ok(analyzer) => {
+                self.analyzer = Some(analyzer);
+                self.engine_error = None;
             }
+            Err(error) => self.engine_error = Some(format!("Couldn't start engine: {error}")),
+        }
+    }
+    ///Starts the engine thinking if it plays the side to move at the end of an unfinished game,
+    ///so browsing earlier moves doesn't play any.
+    fn start_engine_move(&mut self) {
+        self.engine_search = None;
+        if self.outcome.is_some()
+            || !self.is_at_game_end()
+            || self.game_setup.player(self.board.turn) != PlayerKind::Engine
+        {
+            return;
+        }
+        let engine: EngineKind = self.engine_kind();
+        let depth: u32 = match engine {
+            EngineKind::BuiltIn => self.game_setup.engine_level,
+            EngineKind::Uci(_) => self.game_setup.engine_level * UCI_DEPTH_PER_LEVEL,
+        };
+        match Analyzer::start(&engine, &self.board, 1, Some(depth)) {
+            Ok(search) => {
+                self.engine_search = Some(search);
+                self.engine_error = None;
+            }
+            Err(error) => self.engine_error = Some(format!("Couldn't start engine: {error}")),
+        }
+    }
+    ///Restarts the analysis on the current position if analysis mode is on.
+    fn restart_analysis(&mut self) {
+        if self.analyzer.is_some() {
+            self.start_analysis();
         }
     }
     pub fn view(&self) -> Element<'_, Message> {
         let mut screen = Row::new();
         screen = screen.push(self.make_chess_board());
+        if self.analyzer.is_some() {
+            screen = screen.push(self.make_evaluation_bar());
+        }
+        if let Some(editor) = &self.editor {
+            return screen.push(self.make_editor_panel(editor)).into();
+        }
         let mut info_text: Column<'_, Message> = Column::new().width(Length::FillPortion(1));
 
         let title = text("Chess")
@@ -90,10 +1278,11 @@ impl UI {
 
         let turn: Text = text!(
             "{}",
-            if self.board.turn == Color::White {
-                "It's White's Turn"
-            } else {
-                "It's Black's Turn"
+            // A finished position in a variation shows its own ending instead of the game's.
+            match self.board.outcome().or(self.outcome) {
+                Some(outcome) => outcome.to_string(),
+                None if self.board.turn == Color::White => "It's White's Turn".to_string(),
+                None => "It's Black's Turn".to_string(),
             }
         )
         .size(20)
@@ -118,108 +1307,995 @@ impl UI {
         .direction(Direction::Vertical(Scrollbar::new()))
         .height(Length::FillPortion(2));
 
-        let restart_button: Button<Message> = Button::new("Restart Game")
+        let engine_path = text_input("UCI engine path (blank for built-in)", &self.engine_path)
+            .on_input(Message::EnginePathChanged)
+            .width(Length::Fill);
+
+        let analysis_button: Button<Message> = Button::new(if self.analyzer.is_some() {
+            "Stop Analysis"
+        } else {
+            "Start Analysis"
+        })
+        .width(Length::Fill)
+        .on_press(Message::AnalysisButtonPressed);
+
+        let copy_pgn_button: Button<Message> = Button::new("Copy PGN")
             .width(Length::Fill)
-            .on_press(Message::RestartButtonPressed);
+            .on_press(Message::CopyPgnButtonPressed);
+
+        // Changing the moves over the network would leave the two players with different games.
+        let can_edit_moves: bool = self.network.is_none();
+
+        let paste_pgn_button: Button<Message> = Button::new("Paste PGN")
+            .width(Length::Fill)
+            .on_press_maybe(can_edit_moves.then_some(Message::PastePgnButtonPressed));
+
+        let variation_buttons: Row<Message> = row![
+            Button::new("Promote Variation")
+                .width(Length::Fill)
+                .on_press_maybe(
+                    (can_edit_moves && !self.tree.is_mainline(self.current_node))
+                        .then_some(Message::PromoteVariationButtonPressed)
+                ),
+            Button::new("Delete Move")
+                .width(Length::Fill)
+                .on_press_maybe(
+                    (can_edit_moves && self.current_node != MoveTree::ROOT)
+                        .then_some(Message::DeleteMoveButtonPressed)
+                ),
+        ]
+        .spacing(8);
+
+        let flip_board_button: Button<Message> = Button::new("Flip Board")
+            .width(Length::Fill)
+            .on_press(Message::FlipBoardButtonPressed);
+
+        let new_game_button: Button<Message> = Button::new("New Game")
+            .width(Length::Fill)
+            .on_press(Message::NewGameButtonPressed);
+
+        let edit_position_button: Button<Message> = Button::new("Edit Position")
+            .width(Length::Fill)
+            .on_press(Message::EditPositionButtonPressed);
 
         info_text = info_text.push(title);
         info_text = info_text.push(turn);
+        if self.board.variant == VariantKind::ThreeCheck {
+            info_text = info_text.push(
+                text!(
+                    "Checks Given: White {}, Black {}",
+                    self.board.checks_given(Color::White),
+                    self.board.checks_given(Color::Black)
+                )
+                .width(Length::Fill)
+                .align_x(Alignment::Center),
+            );
+        }
+        if self.analyzer.is_some() {
+            info_text = info_text.push(self.make_analysis_lines());
+        }
+        if let Some(error) = &self.engine_error {
+            info_text = info_text.push(text!("{}", error).width(Length::Fill));
+        }
+        if let Some((status, _)) = &self.status {
+            info_text = info_text.push(text!("{}", status).width(Length::Fill));
+        }
+        info_text = info_text.push_maybe(self.make_pending_offer());
+        let bottom_color = if self.flipped {
+            Color::Black
+        } else {
+            Color::White
+        };
+        info_text = info_text.push_maybe(self.make_clock(!bottom_color));
+        info_text = info_text.push_maybe(self.make_pocket(!bottom_color));
         info_text = info_text.push(header);
         info_text = info_text.push(previous_moves);
+        info_text = info_text.push(variation_buttons);
+        info_text = info_text.push(self.make_annotation_editor());
+        info_text = info_text.push_maybe(self.make_pocket(bottom_color));
+        info_text = info_text.push_maybe(self.make_clock(bottom_color));
         info_text = info_text.push(Space::with_height(Length::FillPortion(1)));
-        info_text = info_text.push(restart_button);
+        info_text = info_text.push(self.make_network_panel());
+        info_text = info_text.push(engine_path);
+        info_text = info_text.push(analysis_button);
+        info_text = info_text.push(
+            checkbox("Show Coordinates", self.show_coordinates)
+                .on_toggle(Message::CoordinatesToggled),
+        );
+        info_text = info_text.push(flip_board_button);
+        info_text = info_text.push(copy_pgn_button);
+        info_text = info_text.push(paste_pgn_button);
+        info_text = info_text.push(edit_position_button);
+        info_text = info_text.push(self.make_game_buttons());
+        info_text = info_text.push(new_game_button);
         screen = screen.push(info_text);
-        screen.into()
+
+        let dialog: Container<'_, Message> = if self.promotion_move.is_some() {
+            self.make_promotion_dialog()
+        } else if self.setup_dialog_open {
+            self.make_setup_dialog()
+        } else {
+            return screen.into();
+        };
+        let dialog = center(dialog).style(|_theme: &Theme| {
+            container::Style::default().background(iced::Color::from_rgba8(0, 0, 0, 0.6))
+        });
+        stack![screen, opaque(dialog)].into()
+    }
+    ///Draws the buttons for resigning, offering a draw and asking to take back a move, which
+    ///can be pressed while the game is being played.
+    fn make_game_buttons(&self) -> Row<'_, Message> {
+        let is_playing: bool = self.outcome.is_none()
+            && self.network.as_ref().is_none_or(|network| {
+                network.status() == LinkStatus::Connected && self.local_color.is_some()
+            });
+        let can_ask: bool = is_playing && self.pending_offer.is_none();
+        row![
+            Button::new("Resign")
+                .width(Length::Fill)
+                .on_press_maybe(is_playing.then_some(Message::ResignButtonPressed)),
+            Button::new("Offer Draw")
+                .width(Length::Fill)
+                .on_press_maybe(can_ask.then_some(Message::OfferDrawButtonPressed)),
+            Button::new("Request Takeback")
+                .width(Length::Fill)
+                .on_press_maybe(
+                    (can_ask && self.takeback_node(self.takeback_color()).is_some())
+                        .then_some(Message::RequestTakebackButtonPressed)
+                ),
+        ]
+        .spacing(8)
+    }
+    ///Shows the draw offer or takeback request waiting for an answer, with buttons to answer it
+    ///unless it was made from this screen in a game over the network.
+    fn make_pending_offer(&self) -> Option<Column<'_, Message>> {
+        let offer: Offer = self.pending_offer?;
+        let (color, request) = match offer {
+            Offer::Draw(color) => (color, "offers a draw"),
+            Offer::Takeback(color) => (color, "asks to take back a move"),
+        };
+        let side = if color == Color::White {
+            "White"
+        } else {
+            "Black"
+        };
+        let mut panel: Column<'_, Message> = Column::new().spacing(4).push(
+            text!("{side} {request}")
+                .width(Length::Fill)
+                .align_x(Alignment::Center),
+        );
+        if self.local_color == Some(color) {
+            return Some(
+                panel.push(
+                    text("Waiting for the other player to answer")
+                        .size(14)
+                        .width(Length::Fill)
+                        .align_x(Alignment::Center),
+                ),
+            );
+        }
+        panel = panel.push(
+            row![
+                Button::new("Accept")
+                    .width(Length::Fill)
+                    .on_press(Message::AcceptOfferButtonPressed),
+                Button::new("Decline")
+                    .width(Length::Fill)
+                    .on_press(Message::DeclineOfferButtonPressed),
+            ]
+            .spacing(8),
+        );
+        Some(panel)
+    }
+    ///Draws the controls for hosting or joining a game over the network, or the connection's
+    ///status and the chat once connected.
+    fn make_network_panel(&self) -> Column<'_, Message> {
+        let mut panel: Column<'_, Message> = Column::new().spacing(4);
+        let Some(network) = &self.network else {
+            return panel.push(
+                row![
+                    text_input("Address to join, or port to host", &self.network_address)
+                        .on_input(Message::NetworkAddressChanged)
+                        .width(Length::Fill),
+                    Button::new("Host").on_press(Message::HostButtonPressed),
+                    Button::new("Join").on_press(Message::JoinButtonPressed),
+                ]
+                .spacing(8),
+            );
+        };
+        let status: String = match network.port() {
+            Some(port) => format!("{} (hosting on port {port})", network.status()),
+            None => network.status().to_string(),
+        };
+        panel = panel.push(text(status).width(Length::Fill));
+        for line in self.chat.iter().rev().take(CHAT_LINES_SHOWN).rev() {
+            panel = panel.push(text(line).size(14).width(Length::Fill));
+        }
+        panel.push(
+            row![
+                text_input("Message", &self.chat_input)
+                    .on_input(Message::ChatInputChanged)
+                    .on_submit(Message::SendChatButtonPressed)
+                    .width(Length::Fill),
+                Button::new("Send").on_press(Message::SendChatButtonPressed),
+                Button::new("Disconnect").on_press(Message::DisconnectButtonPressed),
+            ]
+            .spacing(8),
+        )
+    }
+    ///Draws the choice of piece for a pawn that reached the last rank.
+    fn make_promotion_dialog(&self) -> Container<'_, Message> {
+        let mut pieces: Row<'_, Message> = Row::new().spacing(8);
+        for piece_type in self.board.promotion_pieces() {
+            pieces = pieces.push(
+                Button::new(
+                    svg(get_image(self.board.turn, piece_type))
+                        .width(64)
+                        .height(64),
+                )
+                .padding(4)
+                .style(button::text)
+                .on_press(Message::PromotionSelected(Some(piece_type))),
+            );
+        }
+        let dialog: Column<'_, Message> = column![
+            text("Promote to")
+                .size(24)
+                .width(Length::Fill)
+                .align_x(Alignment::Center),
+            pieces,
+            Button::new("Cancel")
+                .width(Length::Fill)
+                .on_press(Message::PromotionSelected(None)),
+        ]
+        .spacing(12)
+        .align_x(Alignment::Center);
+        container(dialog).padding(20).style(container::rounded_box)
+    }
+    fn make_setup_dialog(&self) -> Container<'_, Message> {
+        const LABEL_WIDTH: u16 = 110;
+        let setup: &GameSetup = &self.setup;
+
+        let mut dialog: Column<'_, Message> = Column::new().spacing(12);
+        dialog = dialog.push(
+            text("New Game")
+                .size(24)
+                .width(Length::Fill)
+                .align_x(Alignment::Center),
+        );
+        for color in [Color::White, Color::Black] {
+            let (label, name) = match color {
+                Color::White => ("White", &setup.white_name),
+                Color::Black => ("Black", &setup.black_name),
+            };
+            let name_input = text_input("Name", name).on_input(move |name| {
+                let mut setup = self.setup.clone();
+                match color {
+                    Color::White => setup.white_name = name,
+                    Color::Black => setup.black_name = name,
+                }
+                Message::SetupChanged(setup)
+            });
+            let player = pick_list(PlayerKind::ALL, Some(setup.player(color)), move |player| {
+                let mut setup = self.setup.clone();
+                setup.set_player(color, player);
+                Message::SetupChanged(setup)
+            });
+            dialog = dialog.push(
+                row![text(label).width(LABEL_WIDTH), name_input, player]
+                    .spacing(8)
+                    .align_y(Alignment::Center),
+            );
+        }
+        dialog = dialog.push(
+            row![
+                text!("Engine Level {}", setup.engine_level).width(LABEL_WIDTH),
+                slider(1..=MAX_DEPTH, setup.engine_level, |engine_level| {
+                    Message::SetupChanged(GameSetup {
+                        engine_level,
+                        ..self.setup.clone()
+                    })
+                }),
+            ]
+            .spacing(8)
+            .align_y(Alignment::Center),
+        );
+        dialog = dialog.push(
+            row![
+                text("Time Control").width(LABEL_WIDTH),
+                pick_list(
+                    TimeControl::presets(),
+                    Some(setup.time_control.clone()),
+                    |time_control| {
+                        Message::SetupChanged(GameSetup {
+                            time_control,
+                            ..self.setup.clone()
+                        })
+                    },
+                )
+                .width(Length::Fill),
+            ]
+            .spacing(8)
+            .align_y(Alignment::Center),
+        );
+        dialog = dialog.push(
+            row![
+                text("Variant").width(LABEL_WIDTH),
+                pick_list(VariantKind::ALL, Some(setup.variant), |variant| {
+                    Message::SetupChanged(GameSetup {
+                        variant,
+                        ..self.setup.clone()
+                    })
+                })
+                .width(Length::Fill),
+            ]
+            .spacing(8)
+            .align_y(Alignment::Center),
+        );
+        dialog = dialog.push(
+            row![
+                text("Start From").width(LABEL_WIDTH),
+                pick_list(
+                    StartingPosition::ALL,
+                    Some(setup.starting_position),
+                    |starting_position| {
+                        Message::SetupChanged(GameSetup {
+                            starting_position,
+                            ..self.setup.clone()
+                        })
+                    },
+                )
+                .width(Length::Fill),
+            ]
+            .spacing(8)
+            .align_y(Alignment::Center),
+        );
+        if setup.variant == VariantKind::Chess960
+            && setup.starting_position == StartingPosition::Standard
+        {
+            dialog = dialog.push(
+                text_input(
+                    "Chess960 position number, blank for random",
+                    &setup.chess960_number,
+                )
+                .on_input(|chess960_number| {
+                    Message::SetupChanged(GameSetup {
+                        chess960_number,
+                        ..self.setup.clone()
+                    })
+                }),
+            );
+        }
+        if setup.starting_position == StartingPosition::Fen {
+            dialog = dialog.push(text_input("FEN", &setup.fen).on_input(|fen| {
+                Message::SetupChanged(GameSetup {
+                    fen,
+                    ..self.setup.clone()
+                })
+            }));
+        }
+        if let Some(error) = &self.setup_error {
+            dialog = dialog.push(text!("{}", error));
+        }
+        dialog = dialog.push(
+            row![
+                Button::new("Cancel")
+                    .width(Length::Fill)
+                    .on_press(Message::CancelSetupButtonPressed),
+                Button::new("Start Game")
+                    .width(Length::Fill)
+                    .on_press(Message::StartGameButtonPressed),
+            ]
+            .spacing(8),
+        );
+
+        container(dialog)
+            .width(480)
+            .padding(20)
+            .style(container::rounded_box)
+    }
+    ///Draws the side panel of the position editor, with the piece palette and the rest of the
+    ///position's details.
+    fn make_editor_panel<'a>(&'a self, editor: &'a PositionEditor) -> Column<'a, Message> {
+        const PIECE_TYPES: [PieceType; 6] = [
+            PieceType::King,
+            PieceType::Queen,
+            PieceType::Rook,
+            PieceType::Bishop,
+            PieceType::Knight,
+            PieceType::Pawn,
+        ];
+        const CASTLING_LABELS: [&str; 4] = ["White O-O", "White O-O-O", "Black O-O", "Black O-O-O"];
+        let brush_style = |brush: Option<(Color, PieceType)>| {
+            if editor.brush == brush {
+                button::primary
+            } else {
+                button::text
+            }
+        };
+
+        let mut panel: Column<'_, Message> = Column::new().spacing(8).padding(8);
+        panel = panel.push(
+            text("Edit Position")
+                .size(24)
+                .width(Length::Fill)
+                .align_x(Alignment::Center),
+        );
+        for color in [Color::White, Color::Black] {
+            let mut palette_row: Row<'_, Message> = Row::new().spacing(4);
+            for piece_type in PIECE_TYPES {
+                let brush = Some((color, piece_type));
+                palette_row = palette_row.push(
+                    Button::new(svg(get_image(color, piece_type)).width(40).height(40))
+                        .padding(2)
+                        .style(brush_style(brush))
+                        .on_press(Message::EditorBrushSelected(brush)),
+                );
+            }
+            panel = panel.push(palette_row);
+        }
+        panel = panel.push(
+            Button::new("Remove Pieces")
+                .style(brush_style(None))
+                .on_press(Message::EditorBrushSelected(None)),
+        );
+        let turn_button = |label, color| {
+            Button::new(label)
+                .width(Length::Fill)
+                .style(if editor.turn == color {
+                    button::primary
+                } else {
+                    button::secondary
+                })
+                .on_press(Message::EditorTurnSelected(color))
+        };
+        panel = panel.push(
+            row![
+                turn_button("White to Move", Color::White),
+                turn_button("Black to Move", Color::Black),
+            ]
+            .spacing(8),
+        );
+        let castling_boxes: Vec<Element<'_, Message>> = CASTLING_LABELS
+            .iter()
+            .enumerate()
+            .map(|(index, label)| {
+                checkbox(*label, editor.castling[index])
+                    .on_toggle(move |is_allowed| Message::EditorCastlingToggled(index, is_allowed))
+                    .into()
+            })
+            .collect();
+        panel = panel.push(Row::with_children(castling_boxes).spacing(12).wrap());
+        panel = panel.push(
+            text_input("En passant square, such as e3", &editor.en_passant)
+                .on_input(Message::EditorEnPassantChanged)
+                .width(Length::Fill),
+        );
+        panel = panel.push(text(editor.fen()).size(14).style(text::secondary));
+        let is_valid = match editor.board() {
+            Ok(_) => true,
+            Err(problems) => {
+                for problem in problems {
+                    panel = panel.push(text(problem).width(Length::Fill));
+                }
+                false
+            }
+        };
+        panel = panel.push(Space::with_height(Length::Fill));
+        panel = panel.push(
+            row![
+                Button::new("Clear Board")
+                    .width(Length::Fill)
+                    .on_press(Message::ClearBoardButtonPressed),
+                Button::new("Starting Position")
+                    .width(Length::Fill)
+                    .on_press(Message::StartingPositionButtonPressed),
+            ]
+            .spacing(8),
+        );
+        panel = panel.push(
+            row![
+                Button::new("Cancel")
+                    .width(Length::Fill)
+                    .on_press(Message::CancelEditButtonPressed),
+                Button::new("Copy FEN")
+                    .width(Length::Fill)
+                    .on_press_maybe(is_valid.then_some(Message::CopyFenButtonPressed)),
+                Button::new("Play")
+                    .width(Length::Fill)
+                    .on_press_maybe(is_valid.then_some(Message::PlayPositionButtonPressed)),
+            ]
+            .spacing(8),
+        );
+        panel.width(Length::FillPortion(1))
     }
     fn make_chess_board(&self) -> MouseArea<'_, Message> {
-        let mut chess_board = Column::new().width(Length::FillPortion(2));
-
-        for c in 0..4 {
-            let mut odd_row = Row::new();
-            let mut even_row = Row::new();
-            for r in 0..4 {
-                let white_container: Container<'_, Message> = container(svg(get_image(
-                    self.board[Position::new(r * 2, c * 2)].color,
-                    self.board[Position::new(r * 2, c * 2)].piece_type,
-                )))
-                .width(Length::FillPortion(1))
-                .height(Length::FillPortion(1))
-                .style(|_theme: &Theme| {
-                    container::Style::default().background(color!(0x00E3_C16F))
-                });
-
-                let black_container: Container<'_, Message> = container(svg(get_image(
-                    self.board[Position::new(r * 2 + 1, c * 2)].color,
-                    self.board[Position::new(r * 2 + 1, c * 2)].piece_type,
-                )))
-                .width(Length::FillPortion(1))
-                .height(Length::FillPortion(1))
-                .style(|_theme: &Theme| {
-                    container::Style::default().background(color!(0x00B8_8B4A))
-                });
-
-                odd_row = odd_row.push(white_container);
-                odd_row = odd_row.push(black_container);
-            }
-            chess_board = chess_board.push(odd_row);
-
-            for r in 0..4 {
-                let black_container: Container<'_, Message> = container(svg(get_image(
-                    self.board[Position::new(r * 2, c * 2 + 1)].color,
-                    self.board[Position::new(r * 2, c * 2 + 1)].piece_type,
-                )))
-                .width(Length::FillPortion(1))
-                .height(Length::FillPortion(1))
-                .style(|_theme: &Theme| {
-                    container::Style::default().background(color!(0x00B8_8B4A))
-                });
-
-                let white_container: Container<'_, Message> = container(svg(get_image(
-                    self.board[Position::new(r * 2 + 1, c * 2 + 1)].color,
-                    self.board[Position::new(r * 2 + 1, c * 2 + 1)].piece_type,
-                )))
-                .width(Length::FillPortion(1))
-                .height(Length::FillPortion(1))
-                .style(|_theme: &Theme| {
-                    container::Style::default().background(color!(0x00E3_C16F))
-                });
-
-                even_row = even_row.push(black_container);
-                even_row = even_row.push(white_container);
-            }
-            chess_board = chess_board.push(even_row);
-        }
-
-        mouse_area(chess_board)
-            .on_press(Message::LeftButtonPressed)
-            .on_move(Message::CursorMoved)
-            .on_release(Message::LeftButtonReleased)
+        let mut chess_board = Column::new().width(Length::Fill);
+        // A position being edited may have no king to be in check.
+        let checked_king: Option<Position> = if self.editor.is_some() {
+            None
+        } else {
+            self.board.checked_king()
+        };
+        let board: &Board = self.shown_board();
+        let highlighted_squares: Vec<Position> = board.variant.rules().highlighted_squares(board);
+
+        for y in 0..8 {
+            let mut board_row = Row::new();
+            for x in 0..8 {
+                board_row = board_row.push(self.make_square(
+                    Position::new(x, y),
+                    checked_king,
+                    &highlighted_squares,
+                ));
+            }
+            chess_board = chess_board.push(board_row);
+        }
+
+        let overlay = canvas(self.make_overlay())
+            .width(Length::Fill)
+            .height(Length::Fill);
+
+        mouse_area(
+            stack![chess_board, overlay]
+                .width(Length::FillPortion(2))
+                .height(Length::Fill),
+        )
+        .on_press(Message::LeftButtonPressed)
+        .on_enter(Message::CursorEntered)
+        .on_exit(Message::CursorLeft)
+        .on_move(Message::CursorMoved)
+        .on_release(Message::LeftButtonReleased)
+        .on_right_press(Message::RightButtonPressed)
+        .on_right_release(Message::RightButtonReleased)
+    }
+    ///Draws the square shown at the given screen position, with its piece, highlights and
+    ///coordinates.
+    fn make_square(
+        &self,
+        screen_position: Position,
+        checked_king: Option<Position>,
+        highlighted_squares: &[Position],
+    ) -> Container<'_, Message> {
+        let position = self.orient(screen_position);
+        let board: &Board = self.shown_board();
+        let is_light_square = (position.x + position.y).is_multiple_of(2);
+        let (square_color, label_color) = if is_light_square {
+            (LIGHT_SQUARE_COLOR, DARK_SQUARE_COLOR)
+        } else {
+            (DARK_SQUARE_COLOR, LIGHT_SQUARE_COLOR)
+        };
+
+        let mut square = stack![].width(Length::Fill).height(Length::Fill);
+        if highlighted_squares.contains(&position) {
+            square = square.push(self.make_tint(VARIANT_SQUARE_COLOR));
+        }
+        if board
+            .last_move()
+            .is_some_and(|(from, to)| position == from || position == to)
+        {
+            square = square.push(self.make_tint(LAST_MOVE_COLOR));
+        }
+        if self
+            .premoves
+            .iter()
+            .any(|(start, end)| position == *start || position == *end)
+        {
+            square = square.push(self.make_tint(PREMOVE_COLOR));
+        }
+        if (self.grabbed_piece_pos == Some(position) || self.selected_piece_pos == Some(position))
+            && board[position].piece_type != PieceType::None
+        {
+            square = square.push(self.make_tint(HELD_COLOR));
+        }
+        if checked_king == Some(position) {
+            square = square.push(self.make_check_glow());
+        }
+        if !self.is_lifted(position) {
+            square = square.push(svg(get_image(
+                board[position].color,
+                board[position].piece_type,
+            )));
+        }
+        if self.legal_destinations.contains(&position) {
+            square = square.push(self.make_destination_marker(position));
+        }
+        if self.show_coordinates && screen_position.x == 0 {
+            square = square.push(
+                container(
+                    text!("{}", 8 - position.y)
+                        .size(COORDINATE_SIZE)
+                        .color(label_color),
+                )
+                .padding([1, 3])
+                .align_left(Length::Fill)
+                .align_top(Length::Fill),
+            );
+        }
+        if self.show_coordinates && screen_position.y == 7 {
+            square = square.push(
+                container(
+                    text!("{}", (position.x + 97) as char)
+                        .size(COORDINATE_SIZE)
+                        .color(label_color),
+                )
+                .padding([1, 3])
+                .align_right(Length::Fill)
+                .align_bottom(Length::Fill),
+            );
+        }
+
+        container(square)
+            .width(Length::FillPortion(1))
+            .height(Length::FillPortion(1))
+            .style(move |_theme: &Theme| container::Style::default().background(square_color))
+    }
+    ///Fills a square with a translucent color.
+    fn make_tint(&self, tint_color: iced::Color) -> Container<'_, Message> {
+        container(Space::new(Length::Fill, Length::Fill))
+            .style(move |_theme: &Theme| container::Style::default().background(tint_color))
+    }
+    ///Draws a red glow behind a king in check.
+    fn make_check_glow(&self) -> Container<'_, Message> {
+        let square_size = self.square_size();
+        let glow_size = square_size.width.min(square_size.height) * 0.7;
+        container(Space::new(glow_size, glow_size))
+            .style(move |_theme: &Theme| {
+                container::Style::default()
+                    .background(CHECK_COLOR)
+                    .border(border::rounded(glow_size / 2.0))
+                    .shadow(Shadow {
+                        color: CHECK_COLOR,
+                        offset: Vector::ZERO,
+                        blur_radius: glow_size / 3.0,
+                    })
+            })
+            .center(Length::Fill)
+    }
+    ///Returns the layer above the board with the dragged or snapping back piece.
+    fn make_overlay(&self) -> BoardOverlay {
+        let mut overlay = BoardOverlay::default();
+        let square_size = self.square_size();
+        let side: f32 = square_size.width.min(square_size.height);
+        let mut markings: Vec<Marking> = if self.editor.is_some() {
+            Vec::new()
+        } else {
+            self.tree.markings(self.current_node).to_vec()
+        };
+        // Shows the arrow being drawn before the button is released.
+        if let (Some(start), Some(end)) = (self.marking_start, self.cursor_square()) {
+            if start != end {
+                markings.push(Marking::Arrow(self.marking_color(), start, end));
+            }
+        }
+        for marking in markings {
+            let paint: iced::Color = match marking.color() {
+                MarkingColor::Green => GREEN_MARKING_COLOR,
+                MarkingColor::Red => RED_MARKING_COLOR,
+                MarkingColor::Yellow => YELLOW_MARKING_COLOR,
+                MarkingColor::Blue => BLUE_MARKING_COLOR,
+            };
+            match marking {
+                Marking::Arrow(_, start, end) => overlay.push_arrow(
+                    self.square_center(start),
+                    self.square_center(end),
+                    paint,
+                    side * 0.15,
+                ),
+                Marking::Circle(_, square) => {
+                    overlay.push_circle(self.square_center(square), side * 0.45, paint);
+                }
+            }
+        }
+        if let Some(position) = self.grabbed_piece_pos {
+            let piece = self.shown_board()[position];
+            if piece.piece_type != PieceType::None {
+                overlay.push_piece(
+                    get_image(piece.color, piece.piece_type),
+                    self.cursor_position,
+                    square_size,
+                );
+            }
+        }
+        if let Some(piece_type) = self.pocket_piece.filter(|_| self.cursor_active) {
+            overlay.push_piece(
+                get_image(self.board.turn, piece_type),
+                self.cursor_position,
+                square_size,
+            );
+        }
+        if let Some(snap_back) = &self.snap_back {
+            let elapsed = Instant::now().saturating_duration_since(snap_back.started);
+            let progress = (elapsed.as_secs_f32() / SNAP_BACK_DURATION.as_secs_f32()).min(1.0);
+            let eased_progress = 1.0 - (1.0 - progress) * (1.0 - progress);
+            let target = self.square_center(snap_back.piece.position);
+            let center = snap_back.from + (target - snap_back.from) * eased_progress;
+            overlay.push_piece(
+                get_image(snap_back.piece.color, snap_back.piece.piece_type),
+                center,
+                square_size,
+            );
+        }
+        overlay
+    }
+    ///Draws a dot on an empty square or a ring on a capture that the held piece can move to.
+    fn make_destination_marker(&self, position: Position) -> Container<'_, Message> {
+        let square_size = self.square_size();
+        let size = square_size.width.min(square_size.height);
+        if self.board[position].piece_type == PieceType::None {
+            return center(container(Space::new(size * 0.3, size * 0.3)).style(
+                move |_theme: &Theme| {
+                    container::Style::default()
+                        .background(DESTINATION_COLOR)
+                        .border(border::rounded(size * 0.15))
+                },
+            ));
+        }
+        center(
+            container(Space::new(size, size)).style(move |_theme: &Theme| container::Style {
+                border: Border {
+                    color: DESTINATION_COLOR,
+                    width: size * 0.08,
+                    radius: (size / 2.0).into(),
+                },
+                ..container::Style::default()
+            }),
+        )
+    }
+    fn make_clock(&self, color: Color) -> Option<Container<'_, Message>> {
+        let clock = self.clock.as_ref()?;
+        let is_running = clock.running_side() == Some(color);
+        let time: Text = text!("{}", format_time(clock.remaining(color, Instant::now())))
+            .size(28)
+            .width(Length::Fill)
+            .align_x(Alignment::Center);
+        Some(
+            container(time)
+                .width(Length::Fill)
+                .padding(4)
+                .style(move |_theme: &Theme| {
+                    if is_running {
+                        container::Style::default().background(LIGHT_SQUARE_COLOR)
+                    } else {
+                        container::Style::default()
+                    }
+                }),
+        )
+    }
+    ///Shows the pieces a side holds to drop, which it can click or drag onto the board on its
+    ///turn.
+    fn make_pocket(&self, color: Color) -> Option<Container<'_, Message>> {
+        if !self.board.variant.rules().has_pockets() {
+            return None;
+        }
+        let mut pocket: Row<'_, Message> =
+            Row::new().spacing(8).height(44).align_y(Alignment::Center);
+        for piece_type in POCKET_PIECES {
+            let count: u8 = self.board.pocket_count(color, piece_type);
+            if count == 0 {
+                continue;
+            }
+            let is_held = color == self.board.turn && self.pocket_piece == Some(piece_type);
+            let piece = container(
+                row![
+                    svg(get_image(color, piece_type)).width(36).height(36),
+                    text!("{count}")
+                ]
+                .align_y(Alignment::Center),
+            )
+            .padding(2)
+            .style(move |_theme: &Theme| {
+                if is_held {
+                    container::Style::default().background(HELD_COLOR)
+                } else {
+                    container::Style::default()
+                }
+            });
+            let mut piece: MouseArea<'_, Message> = mouse_area(piece);
+            if color == self.board.turn {
+                piece = piece.on_press(Message::PocketPiecePressed(piece_type));
+            }
+            pocket = pocket.push(piece);
+        }
+        Some(container(pocket).center_x(Length::Fill))
+    }
+    fn make_evaluation_bar(&self) -> Column<'_, Message> {
+        let white_share = self
+            .analysis
+            .lines
+            .first()
+            .map_or(0.5, |line| line.score.white_share());
+        let white_portion = (white_share * 1000.0).round() as u16;
+
+        let black_part: Container<'_, Message> = container(Space::new(Length::Fill, Length::Fill))
+            .height(Length::FillPortion(1000 - white_portion))
+            .style(|_theme: &Theme| container::Style::default().background(color!(0x0040_3A33)));
+        let white_part: Container<'_, Message> = container(Space::new(Length::Fill, Length::Fill))
+            .height(Length::FillPortion(white_portion))
+            .style(|_theme: &Theme| container::Style::default().background(color!(0x00F0_EDE8)));
+
+        column![black_part, white_part]
+            .width(EVALUATION_BAR_WIDTH)
+            .height(Length::Fill)
+    }
+    fn make_analysis_lines(&self) -> Column<'_, Message> {
+        let mut analysis_lines: Column<'_, Message> = Column::new().spacing(4).padding(8);
+        analysis_lines = analysis_lines.push(text!("Depth {}", self.analysis.depth).size(14));
+        for line in &self.analysis.lines {
+            analysis_lines = analysis_lines.push(
+                text!("{}", self.format_line(line))
+                    .size(14)
+                    .width(Length::Fill),
+            );
+        }
+        analysis_lines
+    }
+    ///Returns a principal variation with its score and move numbers.
+    fn format_line(&self, line: &Line) -> String {
+        format!(
+            "{} {}",
+            line.score,
+            format_moves(&line.moves, self.board.fullmove_number, self.board.turn)
+        )
     }
     fn make_previous_moves_table(&self) -> Column<'_, Message> {
         const BOX_WIDTH: u16 = 50;
         const BOX_SPACING: u16 = 40;
 
         let mut previous_moves: Column<'_, Message> = Column::new();
-        for i in 0..self.previous_moves.len() / 2 {
-            let row: Row<Message> = row![
-                text!("{}.", i + 1).width(BOX_WIDTH),
-                text!("{}", self.previous_moves[i * 2]).width(BOX_WIDTH),
-                text!("{}", self.previous_moves[i * 2 + 1]).width(BOX_WIDTH),
-            ]
-            .spacing(BOX_SPACING);
-            previous_moves = previous_moves.push(row);
-        }
-        if self.previous_moves.len() % 2 == 1 {
-            let row: Row<Message> = row![
-                text!("{}.", self.previous_moves.len() / 2 + 1).width(BOX_WIDTH),
-                text!("{}", self.previous_moves[self.previous_moves.len() - 1]).width(BOX_WIDTH),
-                text!("").width(BOX_WIDTH),
-            ]
-            .spacing(BOX_SPACING);
-            previous_moves = previous_moves.push(row);
+        previous_moves = previous_moves.push_maybe(self.make_comment(MoveTree::ROOT));
+        let mut move_row: Row<'_, Message> = Row::new().spacing(BOX_SPACING);
+        let mut is_row_empty = true;
+        let mut needs_number = true;
+        let mut node: NodeId = MoveTree::ROOT;
+        while let Some((main, variations)) = self.tree.children(node).split_first() {
+            let board: &Board = self.tree.board(node);
+            if board.turn == Color::White || needs_number {
+                move_row = move_row.push(text!("{}.", board.fullmove_number).width(BOX_WIDTH));
+            }
+            if board.turn == Color::Black && needs_number {
+                move_row = move_row.push(text!("...").width(BOX_WIDTH));
+            }
+            move_row = move_row.push(
+                self.make_move_button(
+                    *main,
+                    annotate(self.tree.notation(*main), self.tree.nags(*main)),
+                )
+                .width(BOX_WIDTH),
+            );
+            is_row_empty = false;
+            needs_number = false;
+            let comment: Option<Text<'_>> = self.make_comment(*main);
+            // A row ends after Black's move, or early when a comment or variations are shown
+            // below a move.
+            if board.turn == Color::Black || comment.is_some() || !variations.is_empty() {
+                if board.turn == Color::White {
+                    move_row = move_row.push(text!("").width(BOX_WIDTH));
+                }
+                previous_moves = previous_moves.push(move_row);
+                move_row = Row::new().spacing(BOX_SPACING);
+                is_row_empty = true;
+            }
+            if let Some(comment) = comment {
+                previous_moves = previous_moves.push(comment);
+                needs_number = true;
+            }
+            if !variations.is_empty() {
+                let mut elements: Vec<Element<'_, Message>> = Vec::new();
+                for variation in variations {
+                    self.push_variation(*variation, &mut elements);
+                }
+                previous_moves = previous_moves.push(
+                    Row::with_children(elements)
+                        .spacing(4)
+                        .width(Length::Fill)
+                        .wrap(),
+                );
+                needs_number = true;
+            }
+            node = *main;
+        }
+        if !is_row_empty {
+            previous_moves = previous_moves.push(move_row);
         }
         previous_moves
     }
+    ///Adds a variation, with the variations inside it, to the move list as a line in
+    ///parentheses.
+    fn push_variation<'a>(&'a self, node: NodeId, elements: &mut Vec<Element<'a, Message>>) {
+        elements.push(text("(").into());
+        elements.push(
+            self.make_move_button(node, self.numbered_notation(node, true))
+                .into(),
+        );
+        let mut needs_number: bool = self.push_comment(node, elements);
+        let mut line_node: NodeId = node;
+        while let Some((main, variations)) = self.tree.children(line_node).split_first() {
+            elements.push(
+                self.make_move_button(*main, self.numbered_notation(*main, needs_number))
+                    .into(),
+            );
+            needs_number = self.push_comment(*main, elements);
+            for variation in variations {
+                self.push_variation(*variation, elements);
+                needs_number = true;
+            }
+            line_node = *main;
+        }
+        elements.push(text(")").into());
+    }
+    ///Returns a move with its move number, which Black's moves only need after a break in the
+    ///line.
+    fn numbered_notation(&self, node: NodeId, needs_number: bool) -> String {
+        let board: &Board = self
+            .tree
+            .board(self.tree.parent(node).unwrap_or(MoveTree::ROOT));
+        let notation: String = annotate(self.tree.notation(node), self.tree.nags(node));
+        match board.turn {
+            Color::White => format!("{}. {notation}", board.fullmove_number),
+            Color::Black if needs_number => format!("{}... {notation}", board.fullmove_number),
+            Color::Black => notation,
+        }
+    }
+    ///Adds the comment on a node to a variation, returning whether there was one.
+    fn push_comment<'a>(&'a self, node: NodeId, elements: &mut Vec<Element<'a, Message>>) -> bool {
+        match self.make_comment(node) {
+            Some(comment) => {
+                elements.push(comment.into());
+                true
+            }
+            None => false,
+        }
+    }
+    ///Draws the comment on a node, if it has one.
+    fn make_comment(&self, node: NodeId) -> Option<Text<'_>> {
+        let comment: &str = self.tree.comment(node).trim();
+        (!comment.is_empty()).then(|| text(comment).style(text::secondary))
+    }
+    ///Draws the comment box and NAG buttons for the move on the board.
+    fn make_annotation_editor(&self) -> Column<'_, Message> {
+        let placeholder: &str = if self.current_node == MoveTree::ROOT {
+            "Comment on the game"
+        } else {
+            "Comment on this move"
+        };
+        let mut editor: Column<'_, Message> =
+            column![
+                text_input(placeholder, self.tree.comment(self.current_node))
+                    .on_input(Message::CommentChanged)
+                    .width(Length::Fill)
+            ]
+            .spacing(4);
+        if self.current_node != MoveTree::ROOT {
+            let nags: &[u8] = self.tree.nags(self.current_node);
+            let nag_buttons: Vec<Element<'_, Message>> = NAG_GLYPHS
+                .iter()
+                .map(|(nag, glyph)| {
+                    Button::new(text(*glyph))
+                        .style(if nags.contains(nag) {
+                            button::primary
+                        } else {
+                            button::secondary
+                        })
+                        .on_press(Message::NagToggled(*nag))
+                        .into()
+                })
+                .collect();
+            editor = editor.push(Row::with_children(nag_buttons).spacing(4).wrap());
+        }
+        editor
+    }
+    ///Draws a move in the move list that shows its position when clicked.
+    fn make_move_button(&self, node: NodeId, label: String) -> Button<'_, Message> {
+        Button::new(text(label))
+            .padding(0)
+            .style(if node == self.current_node {
+                button::primary
+            } else {
+                button::text
+            })
+            .on_press(Message::MoveSelected(node))
+    }
     pub fn window() -> Settings {
         let icon: Result<Icon, iced::window::icon::Error> =
             from_file_data(include_bytes!("BlackKnight.png"), Some(ImageFormat::Png));
@@ -238,9 +2314,51 @@ impl UI {
             exit_on_close_request: true,
         }
     }
+    pub fn subscription(&self) -> Subscription<Message> {
+        Subscription::batch([
+            self.trigger_window_event(),
+            self.trigger_engine_tick(),
+            self.trigger_clock_tick(),
+            self.trigger_animation_tick(),
+            self.trigger_status_tick(),
+            self.trigger_network_tick(),
+        ])
+    }
     pub fn trigger_window_event(&self) -> Subscription<Message> {
         event::listen().map(Message::WindowEventOccurred)
     }
+    fn trigger_engine_tick(&self) -> Subscription<Message> {
+        if self.analyzer.is_none() && self.engine_search.is_none() {
+            return Subscription::none();
+        }
+        time::every(Duration::from_millis(250)).map(|_| Message::EngineTick)
+    }
+    fn trigger_animation_tick(&self) -> Subscription<Message> {
+        if self.snap_back.is_none() {
+            return Subscription::none();
+        }
+        time::every(Duration::from_millis(16)).map(Message::AnimationTick)
+    }
+    fn trigger_status_tick(&self) -> Subscription<Message> {
+        if self.status.is_none() {
+            return Subscription::none();
+        }
+        time::every(Duration::from_millis(250)).map(Message::StatusTick)
+    }
+    fn trigger_network_tick(&self) -> Subscription<Message> {
+        if self.network.is_none() {
+            return Subscription::none();
+        }
+        time::every(Duration::from_millis(100)).map(|_| Message::NetworkTick)
+    }
+    fn trigger_clock_tick(&self) -> Subscription<Message> {
+        match &self.clock {
+            Some(clock) if clock.running_side().is_some() => {
+                time::every(Duration::from_millis(100)).map(Message::ClockTick)
+            }
+            _ => Subscription::none(),
+        }
+    }
 }
 
 mod images {
@@ -287,3 +2405,119 @@ mod images {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    ///Starts a standard game between two humans on this screen.
+    fn new_game() -> UI {
+        let mut ui = UI::default();
+        ui.start_game(Board::default());
+        ui
+    }
+
+    fn play(ui: &mut UI, san: &str) {
+        let notation: String = ui.board.play_san(san).unwrap();
+        ui.record_move(notation);
+    }
+
+    #[test]
+    fn resigning_gives_the_game_to_the_opponent() {
+        let mut ui = new_game();
+        play(&mut ui, "e4");
+        let _ = ui.update(Message::ResignButtonPressed);
+        assert_eq!(ui.outcome, Some(Outcome::Resignation(Color::White)));
+        assert_eq!(ui.outcome.unwrap().result(), "1-0");
+        assert!(ui.make_pgn().contains("[Result \"1-0\"]"));
+        assert!(!ui.is_human_turn());
+    }
+
+    #[test]
+    fn accepted_draw_offer_ends_the_game() {
+        let mut ui = new_game();
+        play(&mut ui, "e4");
+        let _ = ui.update(Message::OfferDrawButtonPressed);
+        assert_eq!(ui.pending_offer, Some(Offer::Draw(Color::Black)));
+        let _ = ui.update(Message::AcceptOfferButtonPressed);
+        assert_eq!(ui.outcome, Some(Outcome::DrawAgreed));
+        assert_eq!(ui.outcome.unwrap().result(), "1/2-1/2");
+    }
+
+    #[test]
+    fn moving_declines_the_opponents_draw_offer() {
+        let mut ui = new_game();
+        play(&mut ui, "e4");
+        let _ = ui.update(Message::OfferDrawButtonPressed);
+        play(&mut ui, "e5");
+        assert_eq!(ui.pending_offer, Some(Offer::Draw(Color::Black)));
+        play(&mut ui, "Nf3");
+        assert_eq!(ui.pending_offer, None);
+        assert_eq!(ui.outcome, None);
+    }
+
+    #[test]
+    fn other_player_only_sets_its_own_time_after_moving() {
+        let mut ui = UI {
+            local_color: Some(Color::White),
+            game_setup: GameSetup {
+                time_control: TimeControl::presets()[1].clone(),
+                ..GameSetup::default()
+            },
+            ..UI::default()
+        };
+        ui.start_game(Board::default());
+        play(&mut ui, "e4");
+        let remaining =
+            |ui: &UI, color: Color| ui.clock.as_ref().unwrap().remaining(color, Instant::now());
+        ui.handle_net_message(NetMessage::Clock(Duration::ZERO, Duration::ZERO));
+        assert!(remaining(&ui, Color::White) > Duration::from_secs(50));
+        assert!(remaining(&ui, Color::Black) > Duration::from_secs(50));
+
+        ui.handle_net_message(NetMessage::Move("e7e5".to_string()));
+        let half_minute = Duration::from_secs(30);
+        ui.handle_net_message(NetMessage::Clock(Duration::ZERO, half_minute));
+        assert!(remaining(&ui, Color::White) > Duration::from_secs(50));
+        assert_eq!(remaining(&ui, Color::Black), half_minute);
+        ui.handle_net_message(NetMessage::Clock(Duration::ZERO, Duration::ZERO));
+        assert_eq!(remaining(&ui, Color::Black), half_minute);
+    }
+
+    #[test]
+    fn takeback_removes_variations_from_the_position() {
+        let mut ui = new_game();
+        for san in ["e4", "e5", "Nf3"] {
+            play(&mut ui, san);
+        }
+        let before_knight: NodeId = ui.tree.parent(ui.current_node).unwrap();
+        ui.go_to_node(before_knight);
+        play(&mut ui, "Nc3");
+        ui.go_to_node(ui.tree.line_end(MoveTree::ROOT));
+        assert_eq!(ui.takeback_node(Color::White), Some((before_knight, 1)));
+
+        let _ = ui.update(Message::RequestTakebackButtonPressed);
+        assert_eq!(ui.pending_offer, Some(Offer::Takeback(Color::White)));
+        let _ = ui.update(Message::AcceptOfferButtonPressed);
+        assert_eq!(ui.current_node, before_knight);
+        assert!(ui.tree.children(before_knight).is_empty());
+        assert!(ui.is_at_game_end());
+        assert_eq!(ui.outcome, None);
+        assert!(ui.is_human_turn());
+        play(&mut ui, "d4");
+        assert_eq!(ui.tree.line_end(MoveTree::ROOT), ui.current_node);
+    }
+
+    #[test]
+    fn takeback_against_the_engine_includes_its_reply() {
+        let mut ui = new_game();
+        for san in ["e4", "e5"] {
+            play(&mut ui, san);
+        }
+        ui.game_setup.black_player = PlayerKind::Engine;
+        assert_eq!(ui.takeback_color(), Color::White);
+        assert_eq!(ui.takeback_node(Color::White), Some((MoveTree::ROOT, 2)));
+        let _ = ui.update(Message::RequestTakebackButtonPressed);
+        assert_eq!(ui.current_node, MoveTree::ROOT);
+        assert_eq!(ui.board.turn, Color::White);
+    }
+}
@@ -0,0 +1,517 @@
+use crate::{
+    board::{Board, MoveError, Outcome, PositionProblem},
+    piece::{Color, Position},
+};
+use std::{
+    fmt,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+///The rules that make a chess variant different from standard chess. Every hook defaults to
+///the standard rules, so a variant only overrides what it changes.
+pub trait Variant: Sync {
+    ///Returns the board a game of the variant starts from.
+    fn starting_board(&self) -> Board {
+        Board::default()
+    }
+    ///Checks if a move that follows the movement rules may be played, given the boards before
+    ///and after it.
+    fn check_move(&self, _before: &Board, _after: &Board) -> Result<(), MoveError> {
+        Ok(())
+    }
+    ///Returns how the game has ended by the variant's own rules, before looking at the moves
+    ///left.
+    fn outcome(&self, _board: &Board) -> Option<Outcome> {
+        None
+    }
+    ///Returns how the game ends when the side to move has no legal moves.
+    fn outcome_without_moves(&self, board: &Board) -> Outcome {
+        if board.is_check() {
+            Outcome::Checkmate(!board.turn)
+        } else {
+            Outcome::Stalemate
+        }
+    }
+    ///Checks if a problem that standard chess finds with a position also makes it impossible in
+    ///the variant.
+    fn is_problem(&self, _board: &Board, _problem: PositionProblem) -> bool {
+        true
+    }
+    ///Returns the SAN of a move after it was played on the board, changed to show anything
+    ///the variant adds.
+    fn notation(&self, _after: &Board, notation: String) -> String {
+        notation
+    }
+    ///Checks if castling is entered by moving the king onto its rook, as in Chess960.
+    fn castles_onto_rook(&self) -> bool {
+        false
+    }
+    ///Checks if the king has to be kept out of check. Otherwise it's an ordinary piece that can
+    ///be captured, as in Antichess.
+    fn has_royal_king(&self) -> bool {
+        true
+    }
+    ///Checks if a capture blows up the capturing piece and every piece but a pawn next to it, as
+    ///in Atomic.
+    fn explodes_captures(&self) -> bool {
+        false
+    }
+    ///Checks if captured pieces go to the capturer's pocket to be dropped back on the board, as
+    ///in Crazyhouse.
+    fn has_pockets(&self) -> bool {
+        false
+    }
+    ///Returns the squares to highlight on the board, such as squares a king wins by reaching.
+    fn highlighted_squares(&self, _board: &Board) -> Vec<Position> {
+        Vec::new()
+    }
+}
+
+///The variants that can be played.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum VariantKind {
+    #[default]
+    Standard,
+    Chess960,
+    ThreeCheck,
+    KingOfTheHill,
+    Crazyhouse,
+    Atomic,
+    Antichess,
+    Horde,
+    RacingKings,
+}
+
+impl VariantKind {
+    pub const ALL: [Self; 9] = [
+        Self::Standard,
+        Self::Chess960,
+        Self::ThreeCheck,
+        Self::KingOfTheHill,
+        Self::Crazyhouse,
+        Self::Atomic,
+        Self::Antichess,
+        Self::Horde,
+        Self::RacingKings,
+    ];
+
+    ///Returns the rules of the variant.
+    pub fn rules(self) -> &'static dyn Variant {
+        match self {
+            Self::Standard => &Standard,
+            Self::Chess960 => &Chess960,
+            Self::ThreeCheck => &ThreeCheck,
+            Self::KingOfTheHill => &KingOfTheHill,
+            Self::Crazyhouse => &Crazyhouse,
+            Self::Atomic => &Atomic,
+            Self::Antichess => &Antichess,
+            Self::Horde => &Horde,
+            Self::RacingKings => &RacingKings,
+        }
+    }
+    ///Returns the name of the variant in the PGN `Variant` tag.
+    pub fn pgn_name(self) -> &'static str {
+        match self {
+            Self::Standard => "Standard",
+            Self::Chess960 => "Chess960",
+            Self::ThreeCheck => "Three-check",
+            Self::KingOfTheHill => "King of the Hill",
+            Self::Crazyhouse => "Crazyhouse",
+            Self::Atomic => "Atomic",
+            Self::Antichess => "Antichess",
+            Self::Horde => "Horde",
+            Self::RacingKings => "Racing Kings",
+        }
+    }
+    ///Returns the variant named in a PGN `Variant` tag, which is standard chess if unknown.
+    pub fn from_pgn_name(name: &str) -> Self {
+        match name.to_ascii_lowercase().as_str() {
+            "chess960" | "chess 960" | "fischerandom" | "fischer random" => Self::Chess960,
+            "three-check" | "threecheck" | "3-check" | "3check" => Self::ThreeCheck,
+            "king of the hill" | "kingofthehill" | "koth" => Self::KingOfTheHill,
+            "crazyhouse" => Self::Crazyhouse,
+            "atomic" => Self::Atomic,
+            "antichess" | "losing chess" | "losers" | "giveaway" => Self::Antichess,
+            "horde" => Self::Horde,
+            "racing kings" | "racingkings" => Self::RacingKings,
+            _ => Self::Standard,
+        }
+    }
+}
+
+impl fmt::Display for VariantKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Standard => write!(f, "Standard"),
+            Self::Chess960 => write!(f, "Chess960"),
+            Self::ThreeCheck => write!(f, "Three-check"),
+            Self::KingOfTheHill => write!(f, "King of the Hill"),
+            Self::Crazyhouse => write!(f, "Crazyhouse"),
+            Self::Atomic => write!(f, "Atomic"),
+            Self::Antichess => write!(f, "Antichess"),
+            Self::Horde => write!(f, "Horde"),
+            Self::RacingKings => write!(f, "Racing Kings"),
+        }
+    }
+}
+
+///Standard chess, played with the default rules.
+pub struct Standard;
+
+impl Variant for Standard {}
+
+///Fischer Random Chess, which starts from one of 960 shuffled back ranks.
+pub struct Chess960;
+
+impl Variant for Chess960 {
+    fn starting_board(&self) -> Board {
+        Board::chess960(random_chess960_number())
+    }
+    fn castles_onto_rook(&self) -> bool {
+        true
+    }
+}
+
+///Chess where giving check three times also wins.
+pub struct ThreeCheck;
+
+impl Variant for ThreeCheck {
+    fn outcome(&self, board: &Board) -> Option<Outcome> {
+        [Color::White, Color::Black]
+            .into_iter()
+            .find(|color| board.checks_given(*color) >= 3)
+            .map(|winner| Outcome::Variant(Some(winner), "Three Checks"))
+    }
+}
+
+///Chess where bringing the king to one of the four center squares also wins.
+pub struct KingOfTheHill;
+
+impl Variant for KingOfTheHill {
+    fn outcome(&self, board: &Board) -> Option<Outcome> {
+        let hill: Vec<Position> = self.highlighted_squares(board);
+        [Color::White, Color::Black]
+            .into_iter()
+            .find(|color| {
+                board
+                    .king_position(*color)
+                    .is_some_and(|position| hill.contains(&position))
+            })
+            .map(|winner| Outcome::Variant(Some(winner), "King of the Hill"))
+    }
+    fn highlighted_squares(&self, _board: &Board) -> Vec<Position> {
+        vec![
+            Position::new(3, 3),
+            Position::new(4, 3),
+            Position::new(3, 4),
+            Position::new(4, 4),
+        ]
+    }
+}
+
+///Chess where captured pieces can be dropped back on the board as the capturer's own.
+pub struct Crazyhouse;
+
+impl Variant for Crazyhouse {
+    fn notation(&self, after: &Board, notation: String) -> String {
+        // Checkmate is found from the moves on the board, but a drop may still block the check.
+        match notation.strip_suffix('#') {
+            Some(check) if !after.legal_drops().is_empty() => format!("{check}+"),
+            _ => notation,
+        }
+    }
+    fn has_pockets(&self) -> bool {
+        true
+    }
+}
+
+///Chess where a capture blows up the capturing piece and the pieces around it, and blowing up
+///the king wins.
+pub struct Atomic;
+
+impl Variant for Atomic {
+    fn is_problem(&self, board: &Board, problem: PositionProblem) -> bool {
+        // The side to move is left without a king once it has been blown up, which ends the
+        // game.
+        problem != PositionProblem::MissingKing(board.turn)
+    }
+    fn outcome(&self, board: &Board) -> Option<Outcome> {
+        [Color::White, Color::Black]
+            .into_iter()
+            .find(|color| board.king_position(*color).is_none())
+            .map(|loser| Outcome::Variant(Some(!loser), "King Exploded"))
+    }
+    fn notation(&self, after: &Board, notation: String) -> String {
+        // Blowing up the king ends the game like checkmate.
+        if after.king_position(after.turn).is_none() {
+            return format!("{notation}#");
+        }
+        notation
+    }
+    fn explodes_captures(&self) -> bool {
+        true
+    }
+    fn highlighted_squares(&self, board: &Board) -> Vec<Position> {
+        board
+            .last_explosion()
+            .map(Board::surrounding_squares)
+            .unwrap_or_default()
+    }
+}
+
+///Losing chess, where captures have to be made, the king is an ordinary piece, and losing every
+///piece wins.
+pub struct Antichess;
+
+impl Variant for Antichess {
+    fn starting_board(&self) -> Board {
+        // Without a king to keep safe, there's no castling.
+        Board::from_variant_fen(
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w - - 0 1",
+            VariantKind::Antichess,
+        )
+        .unwrap_or_default()
+    }
+    fn check_move(&self, before: &Board, after: &Board) -> Result<(), MoveError> {
+        let opponent: Color = !before.turn;
+        if after.piece_count(opponent) == before.piece_count(opponent) && before.has_capture() {
+            return Err(MoveError::CaptureRequired);
+        }
+        Ok(())
+    }
+    fn outcome(&self, board: &Board) -> Option<Outcome> {
+        [Color::White, Color::Black]
+            .into_iter()
+            .find(|color| board.piece_count(*color) == 0)
+            .map(|winner| Outcome::Variant(Some(winner), "All Pieces Lost"))
+    }
+    fn outcome_without_moves(&self, board: &Board) -> Outcome {
+        Outcome::Variant(Some(board.turn), "Stalemate")
+    }
+    fn has_royal_king(&self) -> bool {
+        false
+    }
+    fn highlighted_squares(&self, board: &Board) -> Vec<Position> {
+        // The pieces that can capture, since one of them has to.
+        board.capturing_pieces()
+    }
+}
+
+///Chess where White has 36 pawns and no king, and Black wins by capturing all of them.
+pub struct Horde;
+
+impl Variant for Horde {
+    fn starting_board(&self) -> Board {
+        Board::from_variant_fen(
+            "rnbqkbnr/pppppppp/8/1PP2PP1/PPPPPPPP/PPPPPPPP/PPPPPPPP/PPPPPPPP w kq - 0 1",
+            VariantKind::Horde,
+        )
+        .unwrap_or_default()
+    }
+    fn is_problem(&self, board: &Board, problem: PositionProblem) -> bool {
+        match problem {
+            PositionProblem::MissingKing(Color::White)
+            | PositionProblem::TooManyPawns(Color::White)
+            | PositionProblem::TooManyPieces(Color::White) => false,
+            PositionProblem::PawnOnBackRank(position) => {
+                position.y == 0 || board[position].color == Color::Black
+            }
+            _ => true,
+        }
+    }
+    fn outcome(&self, board: &Board) -> Option<Outcome> {
+        (board.piece_count(Color::White) == 0)
+            .then_some(Outcome::Variant(Some(Color::Black), "Horde Captured"))
+    }
+}
+
+///Chess without pawns or checks, where the first king to reach the last rank wins.
+pub struct RacingKings;
+
+impl Variant for RacingKings {
+    fn starting_board(&self) -> Board {
+        Board::from_variant_fen(
+            "8/8/8/8/8/8/krbnNBRK/qrbnNBRQ w - - 0 1",
+            VariantKind::RacingKings,
+        )
+        .unwrap_or_default()
+    }
+    fn check_move(&self, _before: &Board, after: &Board) -> Result<(), MoveError> {
+        if after.is_check() {
+            return Err(MoveError::GivesCheck);
+        }
+        Ok(())
+    }
+    fn outcome(&self, board: &Board) -> Option<Outcome> {
+        let has_arrived = |color: Color| {
+            board
+                .king_position(color)
+                .is_some_and(|position| position.y == 0)
+        };
+        // Black gets one more move after White arrives, to draw by arriving too.
+        let can_black_arrive = || {
+            board.turn == Color::Black
+                && board.king_position(Color::Black).is_some_and(|king| {
+                    board
+                        .legal_destinations(king)
+                        .iter()
+                        .any(|position| position.y == 0)
+                })
+        };
+        match (has_arrived(Color::White), has_arrived(Color::Black)) {
+            (true, true) => Some(Outcome::Variant(None, "Both Kings Reached the Goal")),
+            (false, true) => Some(Outcome::Variant(
+                Some(Color::Black),
+                "King Reached the Goal",
+            )),
+            (true, false) if !can_black_arrive() => Some(Outcome::Variant(
+                Some(Color::White),
+                "King Reached the Goal",
+            )),
+            _ => None,
+        }
+    }
+    fn highlighted_squares(&self, _board: &Board) -> Vec<Position> {
+        (0..8).map(|x| Position::new(x, 0)).collect()
+    }
+}
+
+///Picks a Chess960 starting position from the clock, which is random enough to start a game.
+fn random_chess960_number() -> u16 {
+    let nanos: u32 = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.subsec_nanos());
+    (nanos % 960) as u16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_move_hook_removes_legal_moves() {
+        let fen = "8/8/8/8/8/8/k7/6RK w - - 0 1";
+        let g1 = Position::from_algebraic("g1").unwrap();
+        let g2 = Position::from_algebraic("g2").unwrap();
+        let standard: Board = Board::from_fen(fen).unwrap();
+        let racing: Board = Board::from_variant_fen(fen, VariantKind::RacingKings).unwrap();
+        assert!(standard.legal_moves().contains(&(g1, g2)));
+        assert!(!racing.legal_moves().contains(&(g1, g2)));
+        assert_eq!(
+            racing.clone().move_piece(g1, g2),
+            Err(MoveError::GivesCheck)
+        );
+    }
+
+    #[test]
+    fn outcome_hook_ends_the_game() {
+        let fen = "4k3/8/8/8/3K4/8/8/8 b - - 0 1";
+        assert_eq!(Board::from_fen(fen).unwrap().outcome(), None);
+        let hill: Board = Board::from_variant_fen(fen, VariantKind::KingOfTheHill).unwrap();
+        assert_eq!(
+            hill.outcome(),
+            Some(Outcome::Variant(Some(Color::White), "King of the Hill"))
+        );
+    }
+
+    #[test]
+    fn notation_hook_changes_the_san() {
+        let mut standard: Board = Board::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        assert_eq!(standard.play_san("Ra8").as_deref(), Some("Ra8#"));
+        let mut crazyhouse: Board = Board::from_variant_fen(
+            "6k1/5ppp/8/8/8/8/8/R5K1[n] w - - 0 1",
+            VariantKind::Crazyhouse,
+        )
+        .unwrap();
+        assert_eq!(crazyhouse.play_san("Ra8").as_deref(), Some("Ra8+"));
+        assert_eq!(crazyhouse.outcome(), None);
+    }
+
+    #[test]
+    fn three_check_is_won_on_the_third_check() {
+        let fen = "4k3/8/8/8/8/8/8/R3K3 w - - 0 1 +2+0";
+        let mut board: Board = Board::from_variant_fen(fen, VariantKind::ThreeCheck).unwrap();
+        assert_eq!(board.checks_given(Color::White), 2);
+        assert_eq!(board.outcome(), None);
+        assert_eq!(board.play_san("Ra8").as_deref(), Some("Ra8+"));
+        assert_eq!(board.checks_given(Color::White), 3);
+        assert_eq!(
+            board.outcome(),
+            Some(Outcome::Variant(Some(Color::White), "Three Checks"))
+        );
+        assert!(board.to_fen().ends_with(" +3+0"));
+    }
+
+    #[test]
+    fn three_check_counts_round_trip_through_fen() {
+        let fen = "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2 +1+0";
+        let board: Board = Board::from_variant_fen(fen, VariantKind::ThreeCheck).unwrap();
+        assert_eq!(board.checks_given(Color::White), 1);
+        assert_eq!(board.checks_given(Color::Black), 0);
+        assert_eq!(board.to_fen(), fen);
+        let fresh: Board = VariantKind::ThreeCheck.rules().starting_board();
+        assert_eq!(fresh.checks_given(Color::White), 0);
+    }
+
+    #[test]
+    fn king_of_the_hill_is_won_on_a_center_square() {
+        let fen = "4k3/8/8/8/8/3K4/8/8 w - - 0 1";
+        let mut board: Board = Board::from_variant_fen(fen, VariantKind::KingOfTheHill).unwrap();
+        assert_eq!(board.outcome(), None);
+        let mut next_to_hill: Board = board.clone();
+        next_to_hill.play_san("Kc4").unwrap();
+        assert_eq!(next_to_hill.outcome(), None);
+        board.play_san("Kd4").unwrap();
+        assert_eq!(
+            board.outcome(),
+            Some(Outcome::Variant(Some(Color::White), "King of the Hill"))
+        );
+        assert_eq!(board.outcome().unwrap().result(), "1-0");
+    }
+
+    #[test]
+    fn horde_pawns_double_step_by_rank() {
+        let fen = "4k3/8/8/8/8/8/8/P7 w - - 0 1";
+        let mut board: Board = Board::from_variant_fen(fen, VariantKind::Horde).unwrap();
+        assert!(board.clone().play_san("a3").is_some());
+        board.play_san("a2").unwrap();
+        board.play_san("Kd8").unwrap();
+        assert!(board.clone().play_san("a4").is_some());
+        let reread: Board = Board::from_variant_fen(&board.to_fen(), VariantKind::Horde).unwrap();
+        assert!(reread.clone().play_san("a4").is_some());
+
+        board.play_san("a3").unwrap();
+        board.play_san("Ke8").unwrap();
+        assert!(board.clone().play_san("a5").is_none());
+    }
+
+    #[test]
+    fn horde_is_lost_when_every_white_piece_is_captured() {
+        let mut board: Board =
+            Board::from_variant_fen("4k3/8/8/8/8/8/3r4/3P4 b - - 0 1", VariantKind::Horde).unwrap();
+        assert_eq!(board.outcome(), None);
+        board.play_san("Rxd1").unwrap();
+        assert_eq!(
+            board.outcome(),
+            Some(Outcome::Variant(Some(Color::Black), "Horde Captured"))
+        );
+        assert_eq!(board.outcome().unwrap().result(), "0-1");
+    }
+
+    #[test]
+    fn racing_kings_draws_when_black_arrives_right_after_white() {
+        let fen = "8/K6k/8/8/8/8/8/8 w - - 0 1";
+        let mut board: Board = Board::from_variant_fen(fen, VariantKind::RacingKings).unwrap();
+        board.play_san("Ka8").unwrap();
+        assert_eq!(board.outcome(), None);
+        let mut late: Board = board.clone();
+        board.play_san("Kh8").unwrap();
+        assert_eq!(
+            board.outcome(),
+            Some(Outcome::Variant(None, "Both Kings Reached the Goal"))
+        );
+        assert_eq!(board.outcome().unwrap().result(), "1/2-1/2");
+        late.play_san("Kg6").unwrap();
+        assert_eq!(late.outcome().unwrap().result(), "1-0");
+    }
+}